@@ -0,0 +1,97 @@
+use std::io::{ Read, Seek, SeekFrom, Write };
+
+use crate::{ Error, Page, PacketWriter, PageReader, Stream };
+
+/// Open an existing logical stream in `file` to write more
+/// packets to it.
+/// 
+/// The whole file is read to find the last page of the logical
+/// stream with the serial number `serial`. If that page has the
+/// end of stream flag, the flag is cleared in place. The
+/// returned writer writes to the end of the file, and continues
+/// the page sequence number, packet number and granule position
+/// of the logical stream, so the new packets extend the same
+/// logical stream instead of starting a new one.
+/// 
+/// In a chained file, the last logical stream with this serial
+/// number is continued. Its last page has to be the last page of
+/// the file, otherwise [AppendError::StreamNotAtEnd] is returned,
+/// since the new pages would come after pages of other logical
+/// streams. Bytes after the last page that aren't part of any
+/// page return [AppendError::TrailingBytes], since the new pages
+/// would come after them. Damaged files should go through
+/// [repair](crate::repair) or [salvage](crate::salvage) first.
+pub fn append<F: Read + Write + Seek>(mut file: F, serial: i32) -> Result<PacketWriter<F>, Error> {
+	file.seek(SeekFrom::Start(0))?;
+	let mut pages = PageReader::new(&mut file)?;
+	let mut last: Option<(u64, Page)> = None;
+	let mut packets = 0u64;
+	// Whether a page of another logical stream comes after `last`
+	let mut followed = false;
+
+	while let Some((offset, page)) = pages.next_page()? {
+		if page.stream_serial() != serial {
+			followed = last.is_some();
+			continue
+		}
+		followed = false;
+		if page.begins_logical_stream() { packets = 0 }
+		packets += page.finished_packets() as u64;
+		last = Some((offset, page))
+	}
+	drop(pages);
+
+	let (offset, mut page) = last.ok_or(AppendError::StreamNotFound(serial))?;
+	if followed {
+		return Err(AppendError::StreamNotAtEnd(serial).into())
+	}
+	if page.lacing_values().last() == Some(&255) {
+		return Err(AppendError::UnfinishedPacket.into())
+	}
+	let end = offset + page.size() as u64;
+	let length = file.seek(SeekFrom::End(0))?;
+	if length > end {
+		return Err(AppendError::TrailingBytes(length - end).into())
+	}
+
+	if page.ends_logical_stream() {
+		page.set_ends_logical_stream(false);
+		page.set_crc_checksum();
+		file.seek(SeekFrom::Start(offset))?;
+		file.write_all(page.header())?;
+	}
+	file.seek(SeekFrom::Start(end))?;
+
+	let mut stream = Stream::new(serial)?;
+	stream.resume(page.index().wrapping_add(1), packets, page.absgp());
+	Ok(PacketWriter::from_stream(file, stream))
+}
+
+/// An error returned by [append].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppendError {
+	/// There is no logical stream with this serial number.
+	StreamNotFound (i32),
+	/// Pages of other logical streams come after the last page
+	/// of the logical stream with this serial number.
+	StreamNotAtEnd (i32),
+	/// The last page of the logical stream ends in the middle
+	/// of a packet, so the file is cut off.
+	UnfinishedPacket,
+	/// This many bytes that aren't part of any page come after
+	/// the last page.
+	TrailingBytes (u64)
+}
+
+impl std::fmt::Display for AppendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::StreamNotFound(serial) => write!(f, "no logical stream has the serial number {}", serial),
+			Self::StreamNotAtEnd(serial) => write!(f, "the logical stream with the serial number {} is not at the end of the file", serial),
+			Self::UnfinishedPacket => write!(f, "the logical stream ends in the middle of a packet"),
+			Self::TrailingBytes(bytes) => write!(f, "{} bytes that are not part of any page come after the last page", bytes)
+		}
+    }
+}
+
+impl std::error::Error for AppendError {}
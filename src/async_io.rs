@@ -0,0 +1,226 @@
+use std::{
+	collections::{ hash_map::Entry, HashMap, VecDeque },
+	pin::Pin,
+	task::{ Context, Poll }
+};
+use futures_core::Stream as AsyncStream;
+use tokio::io::{ AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf };
+
+use crate::{ Error, Packet, Page, PageOutError, PageWriteError, Stream, SyncState };
+
+/// The number of bytes read from the reader at a time.
+const READ_SIZE: usize = 4096;
+
+/// Reads [Pages](Page) from an [AsyncRead] without blocking.
+/// 
+/// This is the async version of writing bytes to a [SyncState]
+/// and taking pages out of it. Bytes that don't belong to any
+/// page are skipped.
+/// 
+/// ```rust
+/// # async fn example(reader: impl tokio::io::AsyncRead + Unpin) -> Result<(), ogg_xiph::Error> {
+/// use futures_util::StreamExt;
+/// use ogg_xiph::AsyncPageReader;
+/// 
+/// let mut pages = AsyncPageReader::new(reader)?;
+/// while let Some(page) = pages.next().await {
+/// 	println!("Granule position: {}", page?.absgp());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncPageReader<R> {
+	reader: R,
+	sync_state: SyncState,
+	buffer: Box<[u8]>,
+	end_of_file: bool,
+	/// Set after an error the sync state can't recover from.
+	done: bool
+}
+
+impl<R: AsyncRead + Unpin> AsyncPageReader<R> {
+	/// Return a new `AsyncPageReader` reading from `reader`.
+	pub fn new(reader: R) -> Result<Self, Error> {
+		Ok(Self {
+			reader,
+			sync_state: SyncState::new()?,
+			buffer: vec![0; READ_SIZE].into_boxed_slice(),
+			end_of_file: false,
+			done: false
+		})
+	}
+
+	/// Return the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+}
+
+impl<R: AsyncRead + Unpin> AsyncStream for AsyncPageReader<R> {
+	type Item = Result<Page, Error>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = self.get_mut();
+		if this.done { return Poll::Ready(None) }
+
+		loop {
+			match this.sync_state.pages().next() {
+				Some(Ok(page)) => return Poll::Ready(Some(Ok(page))),
+				Some(Err(PageWriteError::OutOfSync)) => continue,
+				Some(Err(error)) => {
+					this.done = true;
+					return Poll::Ready(Some(Err(error.into())))
+				},
+				None => {}
+			}
+
+			if this.end_of_file { return Poll::Ready(None) }
+
+			let mut buffer = ReadBuf::new(&mut this.buffer);
+			match Pin::new(&mut this.reader).poll_read(cx, &mut buffer) {
+				Poll::Pending => return Poll::Pending,
+				Poll::Ready(Err(error)) => return Poll::Ready(Some(Err(error.into()))),
+				Poll::Ready(Ok(())) => {
+					if buffer.filled().is_empty() {
+						this.end_of_file = true
+					} else {
+						this.sync_state.write(buffer.filled())
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Reads [Packets](Packet) from an [AsyncRead] without blocking.
+/// 
+/// Every logical stream in the input gets its own [Stream], and
+/// each packet is returned along with the serial number of the
+/// logical stream it belongs to. Holes in a logical stream are
+/// returned as [OutOfSync](crate::PacketOutError::OutOfSync)
+/// errors without ending the reader.
+pub struct AsyncPacketReader<R> {
+	pages: AsyncPageReader<R>,
+	streams: HashMap<i32, Stream>,
+	packets: VecDeque<Result<(i32, Packet), Error>>
+}
+
+impl<R: AsyncRead + Unpin> AsyncPacketReader<R> {
+	/// Return a new `AsyncPacketReader` reading from `reader`.
+	pub fn new(reader: R) -> Result<Self, Error> {
+		Ok(Self {
+			pages: AsyncPageReader::new(reader)?,
+			streams: HashMap::new(),
+			packets: VecDeque::new()
+		})
+	}
+
+	/// Return the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.pages.into_inner()
+	}
+
+	/// Add a page to its logical stream and queue all of the
+	/// packets that could be completed.
+	fn page_in(&mut self, mut page: Page) -> Result<(), Error> {
+		let serial = page.stream_serial();
+		let stream = match self.streams.entry(serial) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) => entry.insert(Stream::new(serial)?)
+		};
+		stream.page_in(&mut page)?;
+
+		for packet in stream.packets() {
+			self.packets.push_back(packet.map(|packet| (serial, packet)).map_err(Error::from))
+		}
+
+		if page.ends_logical_stream() {
+			self.streams.remove(&serial);
+		}
+
+		Ok(())
+	}
+}
+
+impl<R: AsyncRead + Unpin> AsyncStream for AsyncPacketReader<R> {
+	type Item = Result<(i32, Packet), Error>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = self.get_mut();
+
+		loop {
+			if let Some(packet) = this.packets.pop_front() {
+				return Poll::Ready(Some(packet))
+			}
+
+			match Pin::new(&mut this.pages).poll_next(cx) {
+				Poll::Pending => return Poll::Pending,
+				Poll::Ready(None) => return Poll::Ready(None),
+				Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+				Poll::Ready(Some(Ok(page))) => if let Err(error) = this.page_in(page) {
+					return Poll::Ready(Some(Err(error)))
+				}
+			}
+		}
+	}
+}
+
+/// Writes [Pages](Page) to an [AsyncWrite] without blocking.
+pub struct AsyncPageWriter<W> {
+	writer: W
+}
+
+impl<W: AsyncWrite + Unpin> AsyncPageWriter<W> {
+	/// Return a new `AsyncPageWriter` writing to `writer`.
+	pub fn new(writer: W) -> Self {
+		Self { writer }
+	}
+
+	/// Write a single [Page].
+	pub async fn write_page(&mut self, page: &Page) -> Result<(), Error> {
+		self.writer.write_all(page.header()).await?;
+		self.writer.write_all(page.data()).await?;
+		Ok(())
+	}
+
+	/// Write every [Page] the [Stream] can complete so far,
+	/// as decided by [Stream::page_out].
+	pub async fn write_pages(&mut self, stream: &mut Stream) -> Result<(), Error> {
+		loop {
+			match stream.page_out() {
+				Ok(page) => {
+					let page = page.clone();
+					self.write_page(&page).await?
+				},
+				Err(PageOutError::NeedMoreData) => return Ok(()),
+				Err(error) => return Err(error.into())
+			}
+		}
+	}
+
+	/// Flush every remaining packet in the [Stream] into pages
+	/// and write them, for example at the end of the stream.
+	pub async fn flush_stream(&mut self, stream: &mut Stream) -> Result<(), Error> {
+		loop {
+			match stream.page_flush() {
+				Ok(page) => {
+					let page = page.clone();
+					self.write_page(&page).await?
+				},
+				Err(PageOutError::NeedMoreData) => return Ok(()),
+				Err(error) => return Err(error.into())
+			}
+		}
+	}
+
+	/// Flush the underlying writer.
+	pub async fn flush(&mut self) -> Result<(), Error> {
+		self.writer.flush().await?;
+		Ok(())
+	}
+
+	/// Return the underlying writer.
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
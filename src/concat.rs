@@ -0,0 +1,103 @@
+use std::{
+	collections::{ HashMap, HashSet },
+	io::{ Read, Write }
+};
+
+use crate::{ Error, PageReader };
+
+/// A logical stream that was given a new serial number by
+/// [concat], because an earlier input already used its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reserial {
+	/// The index of the input the logical stream is from.
+	pub input: usize,
+	/// The serial number in the input.
+	pub from: i32,
+	/// The serial number in the output.
+	pub to: i32
+}
+
+/// Join several physical streams one after another into a
+/// single chained physical stream.
+/// 
+/// Pages are copied as they are, except for logical streams
+/// whose serial number was already used by an earlier input.
+/// Those are given the next free serial number, and their
+/// pages get a new checksum. Every logical stream of an input
+/// must end before the next input starts, otherwise this
+/// returns [ConcatError::MissingEndOfStream]. Everything up to
+/// the end of that input is already written by then.
+/// 
+/// Returns every logical stream that was given a new serial
+/// number.
+pub fn concat<I, R, W>(inputs: I, mut writer: W) -> Result<Vec<Reserial>, Error>
+where
+	I: IntoIterator<Item = R>,
+	R: Read,
+	W: Write
+{
+	let mut used: HashSet<i32> = HashSet::new();
+	let mut reserialed = vec![];
+
+	for (input, reader) in inputs.into_iter().enumerate() {
+		let mut pages = PageReader::new(reader)?;
+		// Serial numbers in the input and the output
+		let mut serials: HashMap<i32, i32> = HashMap::new();
+		let mut open: HashSet<i32> = HashSet::new();
+
+		while let Some((_, mut page)) = pages.next_page()? {
+			let serial = page.stream_serial();
+			let new_serial = match serials.get(&serial) {
+				Some(new_serial) => *new_serial,
+				None => {
+					let taken: HashSet<i32> = serials.values().copied().collect();
+					let mut new_serial = serial;
+					while used.contains(&new_serial) || taken.contains(&new_serial) {
+						new_serial = new_serial.wrapping_add(1)
+					}
+					if new_serial != serial {
+						reserialed.push(Reserial { input, from: serial, to: new_serial })
+					}
+					serials.insert(serial, new_serial);
+					new_serial
+				}
+			};
+
+			if page.begins_logical_stream() { open.insert(serial); }
+			if page.ends_logical_stream() { open.remove(&serial); }
+
+			if new_serial != serial {
+				page.set_stream_serial(new_serial);
+				page.set_crc_checksum();
+			}
+			writer.write_all(page.header())?;
+			writer.write_all(page.data())?;
+		}
+
+		if let Some(serial) = open.into_iter().min() {
+			return Err(ConcatError::MissingEndOfStream { input, serial }.into())
+		}
+		used.extend(serials.into_values());
+	}
+
+	writer.flush()?;
+	Ok(reserialed)
+}
+
+/// An error returned by [concat].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConcatError {
+	/// A logical stream of an input has no end of stream page,
+	/// so the next input can't be chained after it.
+	MissingEndOfStream { input: usize, serial: i32 }
+}
+
+impl std::fmt::Display for ConcatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::MissingEndOfStream { input, serial } => write!(f, "logical stream {} of input {} has no end of stream page", serial, input)
+		}
+    }
+}
+
+impl std::error::Error for ConcatError {}
@@ -0,0 +1,335 @@
+use std::{
+	collections::VecDeque,
+	io::{ Read, Write },
+	num::NonZeroUsize
+};
+
+use crate::{ Codec, Error, MinimalOverhead, Packet, PacketWriter, PageReader, Stream, Timebase, Timestamper };
+use crate::repaginate::MAX_PAGE_SIZE;
+
+/// The number of samples Opus needs to decode before its
+/// output is right, 80 ms at 48 kHz.
+const OPUS_PRE_ROLL: u64 = 3840;
+
+/// What [cut] wrote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CutSummary {
+	pub serial: i32,
+	pub codec: Codec,
+	/// The granule position in the input that granule positions
+	/// in the output are counted from. For Opus this includes
+	/// the pre-roll.
+	pub start: u64,
+	/// The granule position in the input the output ends at.
+	pub end: u64,
+	/// The number of packets written after the headers.
+	pub packets: u64
+}
+
+/// Packets along with the granule positions they start and end at.
+struct Group {
+	packets: Vec<Packet>,
+	start: u64,
+	end: u64
+}
+
+/// Writes the part of a logical stream that is kept.
+struct Cutter<W: Write> {
+	writer: Option<W>,
+	output: Option<PacketWriter<W>>,
+	serial: i32,
+	codec: Codec,
+	headers: Vec<Packet>,
+	/// Where playback should start and end, as granule positions.
+	start: u64,
+	end: u64,
+	/// The granule position in the input that granule positions
+	/// in the output are counted from.
+	base: u64,
+	/// Groups before the start, kept for pre-roll.
+	pre_roll: VecDeque<Group>,
+	/// The last group, held back until it's known whether it
+	/// is the last one.
+	held: Option<Group>,
+	done: bool,
+	summary_end: u64,
+	packets: u64
+}
+
+impl<W: Write> Cutter<W> {
+	/// Check whether every packet has its own granule position.
+	fn exact(&self) -> bool {
+		self.codec == Codec::Opus
+	}
+
+	/// Check whether the codec cuts off the end of the last
+	/// packet to match the last granule position.
+	fn trims_end(&self) -> bool {
+		matches!(self.codec, Codec::Opus | Codec::Vorbis)
+	}
+
+	fn group(&mut self, group: Group) -> Result<(), Error> {
+		if self.done { return Ok(()) }
+		if self.output.is_some() { return self.push(group) }
+
+		let pre_roll = if self.exact() { OPUS_PRE_ROLL } else { 0 };
+		let target = self.start.saturating_sub(pre_roll);
+		// Vorbis needs the packet before the start to decode it
+		let extra = (self.codec == Codec::Vorbis) as usize;
+		let reached = group.end > self.start;
+		self.pre_roll.push_back(group);
+		while self.pre_roll.len() >= 2 + extra && self.pre_roll[1 + extra].start <= target {
+			self.pre_roll.pop_front();
+		}
+
+		if reached { self.begin()? }
+		Ok(())
+	}
+
+	/// Write the headers and every group kept for pre-roll.
+	fn begin(&mut self) -> Result<(), Error> {
+		let mut stream = Stream::new(self.serial)?;
+		if self.codec == Codec::Vorbis {
+			// Vorbis only trims the start on the first page, by
+			// giving it a granule position lower than its packets
+			// decode to. So the packet before the start goes on one
+			// page with the group the start is in, and granule
+			// positions are counted from the start.
+			if self.pre_roll.len() > 1 {
+				let reached = self.pre_roll.pop_back().unwrap();
+				let mut before = self.pre_roll.pop_front().unwrap();
+				let mut packets: Vec<Packet> = before.packets.pop().into_iter().collect();
+				packets.extend(reached.packets);
+				self.pre_roll.push_back(Group { packets, ..reached });
+			}
+			self.base = self.start
+		} else {
+			self.base = self.pre_roll[0].start
+		}
+		if !self.exact() {
+			// Pages come out when a group is flushed, unless one
+			// fills up before
+			stream.set_page_policy(MinimalOverhead)
+		}
+		let mut output = PacketWriter::from_stream(self.writer.take().unwrap(), stream);
+
+		let mut headers = std::mem::take(&mut self.headers);
+		if self.codec == Codec::Opus {
+			// Everything up to the start is skipped
+			let pre_skip = (self.start - self.base).min(u16::MAX as u64) as u16;
+			headers[0].data_mut()[10..12].copy_from_slice(&pre_skip.to_le_bytes());
+		}
+		for header in &headers {
+			output.write_header(header)?
+		}
+		self.output = Some(output);
+
+		while let Some(group) = self.pre_roll.pop_front() {
+			self.push(group)?
+		}
+		Ok(())
+	}
+
+	fn push(&mut self, group: Group) -> Result<(), Error> {
+		if self.done { return Ok(()) }
+		if group.start >= self.end {
+			self.done = true;
+			return Ok(())
+		}
+
+		self.done = group.end >= self.end;
+		if let Some(held) = self.held.replace(group) {
+			self.emit(held, false)?
+		}
+		Ok(())
+	}
+
+	fn emit(&mut self, group: Group, last: bool) -> Result<(), Error> {
+		let end = if last && self.trims_end() { group.end.min(self.end) } else { group.end };
+		let count = group.packets.len();
+		let exact = self.exact();
+		let output = self.output.as_mut().unwrap();
+
+		for (index, mut packet) in group.packets.into_iter().enumerate() {
+			let final_packet = index + 1 == count;
+			// Every packet keeps its granule position, if it's known,
+			// in case a page ends after it
+			let absgp = match packet.absgp() {
+				_ if final_packet => end - self.base,
+				u64::MAX => u64::MAX,
+				absgp => absgp.min(end).saturating_sub(self.base)
+			};
+			packet.set_absgp(absgp);
+			packet.set_begins_logical_stream(false);
+			packet.set_ends_logical_stream(last && final_packet);
+			output.write_packet(&packet)?;
+			self.packets += 1;
+		}
+		if !exact {
+			// Keep the pages of the input, so every page has the
+			// right granule position
+			output.flush_pages_with_max_size(NonZeroUsize::new(MAX_PAGE_SIZE).unwrap())?
+		}
+
+		self.summary_end = end;
+		Ok(())
+	}
+
+	fn finish(mut self) -> Result<CutSummary, Error> {
+		let held = match self.held.take() {
+			None => return Err(CutError::OutOfRange.into()),
+			Some(held) => held
+		};
+		self.emit(held, true)?;
+		self.output.take().unwrap().into_inner()?;
+
+		Ok(CutSummary {
+			serial: self.serial,
+			codec: self.codec,
+			start: self.base,
+			end: self.summary_end,
+			packets: self.packets
+		})
+	}
+}
+
+/// Split the packets completed on a page into groups.
+/// 
+/// If `exact` and every packet has a granule position, every
+/// packet gets its own group, starting where the one before ends.
+fn groups(exact: bool, packets: Vec<Packet>, start: u64, end: u64) -> Vec<Group> {
+	if !exact || packets.iter().any(|packet| packet.absgp() == u64::MAX) {
+		return vec![Group { packets, start, end }]
+	}
+
+	let mut start = start;
+	packets.into_iter()
+		.map(|packet| {
+			let group = Group { start, end: packet.absgp(), packets: vec![packet] };
+			start = group.end;
+			group
+		})
+		.collect()
+}
+
+/// Copy the part of a logical stream between `start` and `end`,
+/// in seconds, into a new physical stream.
+/// 
+/// The header packets are copied, followed by the packets that
+/// are needed to play from `start`, with granule positions
+/// counted from the first one. How close the cut gets depends
+/// on the codec:
+/// 
+/// - Opus is cut at the packets around `start` and `end`, with
+///   80 ms of pre-roll. The pre-skip in the header and the last
+///   granule position are set so playback starts and ends at
+///   the exact sample.
+/// - Vorbis, FLAC and Speex are cut at the pages around `start`
+///   and `end`. Vorbis also keeps the packet before the page
+///   `start` is on, which it needs to decode that page, and the
+///   first and last granule positions are set so playback starts
+///   and ends at the exact sample.
+/// - Other codecs return [CutError::Unsupported].
+/// 
+/// Only the first logical stream is cut. Use [extract](crate::extract)
+/// first for a multiplexed physical stream.
+pub fn cut<R: Read, W: Write>(reader: R, writer: W, start: f64, end: Option<f64>) -> Result<CutSummary, Error> {
+	let mut pages = PageReader::new(reader)?;
+	let mut page = loop {
+		match pages.next_page()? {
+			None => return Err(CutError::NoStream.into()),
+			Some((_, page)) if page.begins_logical_stream() => break page,
+			Some(_) => {}
+		}
+	};
+
+	let serial = page.stream_serial();
+	let codec = Codec::identify(page.data());
+	let (timebase, header_packets) = match codec {
+		Codec::Opus | Codec::Vorbis | Codec::Flac | Codec::Speex => (
+			Timebase::from_header(codec, page.data()).ok_or(CutError::Unsupported(codec))?,
+			codec.header_packets(page.data()).ok_or(CutError::Unsupported(codec))?
+		),
+		_ => return Err(CutError::Unsupported(codec).into())
+	};
+	let granule = |seconds: f64| {
+		timebase.pre_skip + (seconds.max(0.0) * timebase.numerator as f64 / timebase.denominator as f64).round() as u64
+	};
+	let start = granule(start);
+	let end = end.map(granule).unwrap_or(u64::MAX);
+	if end <= start { return Err(CutError::OutOfRange.into()) }
+
+	let mut cutter = Cutter {
+		writer: Some(writer),
+		output: None,
+		serial,
+		codec,
+		headers: vec![],
+		start,
+		end,
+		base: 0,
+		pre_roll: VecDeque::new(),
+		held: None,
+		done: false,
+		summary_end: 0,
+		packets: 0
+	};
+	// Gives every packet the granule position it ends at
+	let mut input = Timestamper::new(serial)?;
+	let mut last_end = None;
+	let mut packets = vec![];
+	let mut headers = 0;
+
+	loop {
+		if page.stream_serial() == serial {
+			input.page_in(&mut page)?;
+			for packet in input.packets() {
+				if headers < header_packets {
+					cutter.headers.push(packet);
+					headers += 1
+				} else {
+					packets.push(packet)
+				}
+			}
+
+			if !packets.is_empty() && page.absgp() != u64::MAX {
+				let start = last_end.or(input.start()).unwrap_or(0);
+				for group in groups(cutter.exact(), std::mem::take(&mut packets), start, page.absgp()) {
+					cutter.group(group)?
+				}
+				last_end = Some(page.absgp())
+			}
+			if page.ends_logical_stream() || cutter.done { break }
+		}
+
+		page = match pages.next_page()? {
+			None => break,
+			Some((_, page)) => page
+		};
+	}
+
+	cutter.finish()
+}
+
+/// An error returned by [cut].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CutError {
+	/// There is no logical stream to cut.
+	NoStream,
+	/// The codec of the logical stream can't be cut.
+	Unsupported (Codec),
+	/// Nothing of the logical stream is in the time range.
+	OutOfRange
+}
+
+impl std::fmt::Display for CutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::NoStream => write!(f, "there is no logical stream to cut"),
+			Self::Unsupported(codec) => write!(f, "cutting {} streams is not supported", codec),
+			Self::OutOfRange => write!(f, "nothing of the logical stream is in the time range")
+		}
+    }
+}
+
+impl std::error::Error for CutError {}
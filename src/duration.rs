@@ -0,0 +1,254 @@
+use std::{
+	collections::{ HashMap, HashSet },
+	io::{ Read, Seek, SeekFrom }
+};
+
+use crate::{ Codec, Error, Page, PageReader, Timebase, Timestamper };
+use crate::page::*;
+
+/// How many bytes are read at once while scanning backward.
+const CHUNK_SIZE: u64 = 1 << 17;
+
+/// The largest a page can be: a full header and 255 full segments.
+const MAX_PAGE_SIZE: u64 = 27 + 255 + 255 * 255;
+
+/// How far into a link pages are read to find where its logical
+/// streams start.
+const START_SIZE: u64 = 1 << 20;
+
+/// The duration of one logical stream, as found by [duration].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StreamDuration {
+	/// The index of the link the logical stream is in.
+	pub link: usize,
+	pub serial: i32,
+	pub codec: Codec,
+	/// The granule position the first data packet starts at, if
+	/// it could be found, see [Timestamper::start].
+	pub start: Option<u64>,
+	/// The granule position of the last page with one, if any
+	/// was found.
+	pub absgp: Option<u64>,
+	/// The time from `start` to `absgp` in seconds, if the codec
+	/// has a [Timebase]. A logical stream is taken to start at 0
+	/// if `start` isn't known.
+	pub seconds: Option<f64>
+}
+
+/// The duration of a physical stream, as found by [duration].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileDuration {
+	/// Every logical stream, in the order they begin.
+	pub streams: Vec<StreamDuration>,
+	/// The sum of the longest logical stream in every link, in
+	/// seconds.
+	pub seconds: f64
+}
+
+/// Reads pages out of parts of a file.
+struct Scanner<F> {
+	file: F,
+	length: u64
+}
+
+impl<F: Read + Seek> Scanner<F> {
+	/// Read every page that is whole within `start..end`, along
+	/// with their offsets.
+	fn pages(&mut self, start: u64, end: u64) -> Result<Vec<(u64, Page)>, Error> {
+		self.file.seek(SeekFrom::Start(start))?;
+		let mut pages = PageReader::new((&mut self.file).take(end - start))?;
+		let mut found = vec![];
+		while let Some((offset, page)) = pages.next_page()? {
+			found.push((start + offset, page))
+		}
+		Ok(found)
+	}
+
+	/// Return the first page at or after `start` that begins
+	/// before `end`.
+	fn next_page(&mut self, start: u64, end: u64) -> Result<Option<(u64, Page)>, Error> {
+		let mut chunk_start = start;
+		while chunk_start < end {
+			let chunk_end = (chunk_start + CHUNK_SIZE).min(self.length);
+			if let Some((offset, page)) = self.pages(chunk_start, chunk_end)?.into_iter().next() {
+				return Ok(Some((offset, page)).filter(|(offset, _)| *offset < end))
+			}
+			if chunk_end == self.length { break }
+			// A page that starts in this chunk can end in the next
+			chunk_start = chunk_end.saturating_sub(MAX_PAGE_SIZE).max(chunk_start + 1)
+		}
+		Ok(None)
+	}
+
+	/// Read the header of the page at `offset`, if one starts there
+	/// and ends within the file, along with the size of the page.
+	fn header_at(&mut self, offset: u64) -> Result<Option<(Vec<u8>, u64)>, Error> {
+		let mut header = vec![];
+		self.file.seek(SeekFrom::Start(offset))?;
+		(&mut self.file).take(HEADER_SIZE_MIN as u64).read_to_end(&mut header)?;
+		if validate_header(&header).is_err() { return Ok(None) }
+		let segments = header[HEADER_SEGMENTS] as usize;
+		(&mut self.file).take(segments as u64).read_to_end(&mut header)?;
+		if header.len() < HEADER_SIZE_MIN + segments { return Ok(None) }
+
+		let size = header.len() as u64 + header[HEADER_SIZE_MIN..].iter().map(|value| *value as u64).sum::<u64>();
+		Ok(Some((header, size)).filter(|_| offset + size <= self.length))
+	}
+
+	/// Return the offset the link with the logical streams in
+	/// `serials` ends at, by reading the headers of its pages
+	/// forward from `start`. It ends after every logical stream
+	/// had its end of stream page, or at the next beginning of
+	/// stream page if one didn't.
+	fn link_end(&mut self, mut start: u64, serials: &HashSet<i32>) -> Result<u64, Error> {
+		let mut open = serials.clone();
+		while !open.is_empty() && start < self.length {
+			let (header, size) = match self.header_at(start)? {
+				Some(found) => found,
+				// Not a page, so go on from the next one that is
+				None => match self.next_page(start + 1, self.length)? {
+					Some((offset, page)) => {
+						start = offset;
+						(page.header().to_vec(), page.size() as u64)
+					},
+					None => return Ok(self.length)
+				}
+			};
+			if header[HEADER_TYPE] & HEADER_TYPE_BEGINNING != 0 { return Ok(start) }
+			if header[HEADER_TYPE] & HEADER_TYPE_END != 0 {
+				open.remove(&i32::from_le_bytes(header[HEADER_PAGE_SERIAL_NUMBER..HEADER_PAGE_SERIAL_NUMBER + 4].try_into().unwrap()));
+			}
+			start += size
+		}
+		Ok(start.min(self.length))
+	}
+
+	/// Find the granule position every logical stream in `serials`
+	/// starts at, by reading pages forward from `start` until every
+	/// one had a page with data or `end` is reached.
+	fn starts(&mut self, start: u64, end: u64, serials: &HashSet<i32>) -> Result<HashMap<i32, u64>, Error> {
+		let mut timestampers = HashMap::new();
+		for serial in serials {
+			timestampers.insert(*serial, Timestamper::new(*serial)?);
+		}
+
+		let end = end.min(start + START_SIZE);
+		let mut chunk_start = start;
+		while chunk_start < end && timestampers.values().any(|timestamper| !timestamper.started()) {
+			let chunk_end = (chunk_start + CHUNK_SIZE).min(self.length);
+			let pages = self.pages(chunk_start, chunk_end)?;
+			let next = pages.last().map(|(offset, page)| offset + page.size() as u64);
+			for (_, mut page) in pages {
+				if let Some(timestamper) = timestampers.get_mut(&page.stream_serial()) {
+					timestamper.page_in(&mut page)?;
+					timestamper.packets().for_each(drop)
+				}
+			}
+
+			chunk_start = match next {
+				Some(next) => next,
+				None if chunk_end == self.length => break,
+				// A page that starts in this chunk can end in the next
+				None => chunk_end.saturating_sub(MAX_PAGE_SIZE).max(chunk_start + 1)
+			}
+		}
+
+		Ok(timestampers.into_iter()
+			.filter_map(|(serial, timestamper)| Some((serial, timestamper.start()?)))
+			.collect())
+	}
+
+	/// Scan backward from `end` to `start` for the last page with
+	/// a granule position of every serial number in `serials`, or
+	/// the last page of any serial number if `serials` is empty.
+	fn last_pages(&mut self, start: u64, end: u64, serials: &HashSet<i32>) -> Result<HashMap<i32, (u64, Page)>, Error> {
+		let mut found: HashMap<i32, (u64, Page)> = HashMap::new();
+		let mut chunk_end = end;
+		while chunk_end > start {
+			let chunk_start = chunk_end.saturating_sub(CHUNK_SIZE).max(start);
+			let pages = self.pages(chunk_start, chunk_end)?;
+			for (offset, page) in pages.iter().rev() {
+				let serial = page.stream_serial();
+				if serials.is_empty() {
+					found.insert(serial, (*offset, page.clone()));
+					return Ok(found)
+				}
+				if serials.contains(&serial) && page.absgp() != u64::MAX && !found.contains_key(&serial) {
+					found.insert(serial, (*offset, page.clone()));
+				}
+			}
+			if !found.is_empty() && found.len() == serials.len() { break }
+
+			// Pages before the first one found here end before it,
+			// but one without any page found could end in this chunk
+			chunk_end = match pages.first() {
+				Some((offset, _)) => *offset,
+				None if chunk_start == start => start,
+				None => (chunk_start + MAX_PAGE_SIZE).min(chunk_end - 1)
+			};
+		}
+		Ok(found)
+	}
+}
+
+/// Find the duration of every logical stream in a physical stream,
+/// without reading every page.
+/// 
+/// The beginning of stream pages of every link are read to find
+/// the logical streams and their codecs. The last page of every
+/// logical stream is found by reading backward from the end of
+/// its link in chunks, and only pages with a valid checksum are
+/// used. In a chained physical stream, every link ends after
+/// the end of stream pages of all its logical streams, which are
+/// found by reading the headers of its pages but not their data.
+/// 
+/// The duration of a logical stream is the time from where its
+/// first data packet starts to its last granule position, see
+/// [Timebase::seconds_between]. Where it starts is found from the first
+/// pages of the link, as [Timestamper::start] does.
+pub fn duration<F: Read + Seek>(mut file: F) -> Result<FileDuration, Error> {
+	let length = file.seek(SeekFrom::End(0))?;
+	let mut scanner = Scanner { file, length };
+	let mut streams = vec![];
+	let mut seconds = 0.0;
+
+	let mut link_start = 0;
+	let mut link = 0;
+	while let Some((_, first)) = scanner.next_page(link_start, length)? {
+		// Every beginning of stream page of the link
+		let mut timebases: HashMap<i32, (Codec, Option<Timebase>)> = HashMap::new();
+		let mut serials = vec![];
+		let mut data_start = link_start;
+		let mut next = Some((link_start, first));
+		while let Some((offset, page)) = next {
+			let serial = page.stream_serial();
+			if !(page.begins_logical_stream() || serials.is_empty()) || timebases.contains_key(&serial) { break }
+			let codec = if page.begins_logical_stream() { Codec::identify(page.data()) } else { Codec::Unknown };
+			timebases.insert(serial, (codec, Timebase::from_header(codec, page.data())));
+			serials.push(serial);
+			data_start = offset + page.size() as u64;
+			next = scanner.next_page(data_start, length)?
+		}
+		let in_link: HashSet<i32> = serials.iter().copied().collect();
+
+		let link_end = scanner.link_end(data_start, &in_link)?;
+		let starts = scanner.starts(link_start, link_end, &in_link)?;
+		let last = scanner.last_pages(link_start, link_end, &in_link)?;
+		let mut longest: f64 = 0.0;
+		for serial in serials {
+			let (codec, timebase) = timebases[&serial];
+			let start = starts.get(&serial).copied();
+			let absgp = last.get(&serial).map(|(_, page)| page.absgp());
+			let stream_seconds = timebase.zip(absgp)
+				.map(|(timebase, absgp)| timebase.seconds_between(start.unwrap_or(0), absgp));
+			longest = longest.max(stream_seconds.unwrap_or(0.0));
+			streams.push(StreamDuration { link, serial, codec, start, absgp, seconds: stream_seconds })
+		}
+		seconds += longest;
+
+		link_start = link_end;
+		link += 1
+	}
+
+	Ok(FileDuration { streams, seconds })
+}
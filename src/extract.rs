@@ -0,0 +1,130 @@
+use std::{
+	collections::{ HashMap, HashSet },
+	io::{ Read, Write }
+};
+
+use crate::{ Codec, Error, PageReader };
+
+/// Picks logical streams by serial number or codec.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+	Serial (i32),
+	Codec (Codec)
+}
+
+impl Selection {
+	/// Check whether a logical stream is selected.
+	pub fn matches(&self, serial: i32, codec: Codec) -> bool {
+		match self {
+			Self::Serial(selected) => *selected == serial,
+			Self::Codec(selected) => *selected == codec
+		}
+	}
+}
+
+/// Copy the pages of the logical streams that `keep` picks,
+/// given the index of their link, their serial number and
+/// their codec.
+/// 
+/// The codec is found from the beginning of stream page. Pages of
+/// logical streams that didn't begin in the reader are only kept
+/// if `keep` picks them as [Codec::Unknown].
+/// 
+/// Returns the serial numbers that were kept, in order.
+fn filter_streams<R, W, F>(reader: R, mut writer: W, mut keep: F) -> Result<Vec<i32>, Error>
+where
+	R: Read,
+	W: Write,
+	F: FnMut(usize, i32, Codec) -> bool
+{
+	let mut pages = PageReader::new(reader)?;
+	let mut kept = vec![];
+	let mut streams: HashMap<i32, bool> = HashMap::new();
+	let mut open: HashSet<i32> = HashSet::new();
+	let mut link = 0;
+	let mut link_pages = 0;
+
+	while let Some((_, page)) = pages.next_page()? {
+		let serial = page.stream_serial();
+		if page.begins_logical_stream() {
+			if open.is_empty() && link_pages > 0 {
+				// A new link of a chained stream
+				streams.clear();
+				link += 1;
+				link_pages = 0
+			}
+			open.insert(serial);
+			streams.remove(&serial);
+		}
+		if page.ends_logical_stream() {
+			open.remove(&serial);
+		}
+		link_pages += 1;
+
+		let keep_page = *streams.entry(serial).or_insert_with(|| {
+			let codec = if page.begins_logical_stream() { Codec::identify(page.data()) } else { Codec::Unknown };
+			let keep_stream = keep(link, serial, codec);
+			if keep_stream { kept.push(serial) }
+			keep_stream
+		});
+		if keep_page {
+			writer.write_all(page.header())?;
+			writer.write_all(page.data())?;
+		}
+	}
+
+	writer.flush()?;
+	Ok(kept)
+}
+
+/// Copy a single logical stream out of a multiplexed physical
+/// stream, picked by `selection`.
+/// 
+/// If several logical streams match, the first one to begin is
+/// picked. In a chained physical stream, one logical stream is
+/// picked from every link. Pages are copied as they are.
+/// 
+/// Returns the serial numbers that were picked, or
+/// [ExtractError::NotFound] if none matched.
+pub fn extract<R: Read, W: Write>(reader: R, writer: W, selection: Selection) -> Result<Vec<i32>, Error> {
+	// The last link a logical stream was picked from
+	let mut picked: Option<usize> = None;
+	let kept = filter_streams(reader, writer, |link, serial, codec| {
+		if picked != Some(link) && selection.matches(serial, codec) {
+			picked = Some(link);
+			true
+		} else { false }
+	})?;
+
+	if kept.is_empty() { return Err(ExtractError::NotFound(selection).into()) }
+	Ok(kept)
+}
+
+/// Copy a physical stream without the logical streams that
+/// match any of `selections`.
+/// 
+/// Pages are copied as they are. Returns the serial numbers
+/// that were kept.
+pub fn drop_streams<R: Read, W: Write>(reader: R, writer: W, selections: &[Selection]) -> Result<Vec<i32>, Error> {
+	filter_streams(reader, writer, |_, serial, codec| {
+		!selections.iter().any(|selection| selection.matches(serial, codec))
+	})
+}
+
+/// An error returned by [extract].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtractError {
+	/// No logical stream matched the selection.
+	NotFound (Selection)
+}
+
+impl std::fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::NotFound(Selection::Serial(serial)) => write!(f, "no logical stream has the serial number {}", serial),
+			Self::NotFound(Selection::Codec(codec)) => write!(f, "no logical stream carries {}", codec)
+		}
+    }
+}
+
+impl std::error::Error for ExtractError {}
@@ -0,0 +1,289 @@
+use std::{
+	collections::HashMap,
+	io::{ Read, Seek, SeekFrom, Write }
+};
+
+use crate::{ Error, Page, PageReader, PageRef, scan_pages };
+use crate::page::*;
+
+/// The bytes every sidecar file starts with.
+const MAGIC: &[u8; 4] = b"OgIx";
+
+/// The version of the sidecar format that is written.
+const VERSION: u16 = 1;
+
+/// Where a page is in a physical stream and what is on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageEntry {
+	/// The offset of the first byte of the page.
+	pub offset: u64,
+	/// The size of the page in bytes, header included.
+	pub length: u32,
+	pub serial: i32,
+	/// The sequence number of the page.
+	pub index: u32,
+	pub absgp: u64,
+	/// The header type flags of the page.
+	pub flags: u8,
+	/// The number of packets that finish on the page.
+	pub packets: u8
+}
+
+impl PageEntry {
+	/// The size of an entry in a sidecar file.
+	const SIZE: usize = 30;
+
+	/// Return the entry of a page found at `offset`, from its
+	/// header and segment table.
+	fn new(offset: u64, header: &[u8]) -> Self {
+		let field = |at: usize, size: usize| &header[at..at + size];
+		let lacing_values = &header[HEADER_SIZE_MIN..];
+		Self {
+			offset,
+			length: (header.len() + lacing_values.iter().map(|value| *value as usize).sum::<usize>()) as u32,
+			serial: i32::from_le_bytes(field(HEADER_PAGE_SERIAL_NUMBER, 4).try_into().unwrap()),
+			index: u32::from_le_bytes(field(HEADER_SEQUENCE_NUMBER, 4).try_into().unwrap()),
+			absgp: u64::from_le_bytes(field(HEADER_GRANULE_POSITION, 8).try_into().unwrap()),
+			flags: header[HEADER_TYPE],
+			packets: lacing_values.iter().filter(|value| **value < 255).count() as u8
+		}
+	}
+
+	fn write_to(&self, bytes: &mut Vec<u8>) {
+		bytes.extend_from_slice(&self.offset.to_le_bytes());
+		bytes.extend_from_slice(&self.length.to_le_bytes());
+		bytes.extend_from_slice(&self.serial.to_le_bytes());
+		bytes.extend_from_slice(&self.index.to_le_bytes());
+		bytes.extend_from_slice(&self.absgp.to_le_bytes());
+		bytes.push(self.flags);
+		bytes.push(self.packets);
+	}
+
+	fn read_from(bytes: &[u8; Self::SIZE]) -> Self {
+		Self {
+			offset: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+			length: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+			serial: i32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+			index: u32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+			absgp: u64::from_le_bytes(bytes[20..28].try_into().unwrap()),
+			flags: bytes[28],
+			packets: bytes[29]
+		}
+	}
+}
+
+/// An index of every page in a physical stream, which can be
+/// saved to a sidecar file to seek without reading the stream.
+/// 
+/// The index can be built while the stream is read, with
+/// [add](PageIndex::add), or all at once with [build](PageIndex::build).
+/// 
+/// A sidecar file written with [write_to](PageIndex::write_to)
+/// records the size of the source file and the checksum of its
+/// first page. [load](PageIndex::load) checks them, so an index
+/// isn't used for a file that has changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageIndex {
+	entries: Vec<PageEntry>,
+	/// The granule position and entry of every page with one,
+	/// sorted by granule position, for every serial number.
+	granules: HashMap<i32, Vec<(u64, usize)>>,
+	file_size: u64,
+	first_checksum: u32
+}
+
+impl PageIndex {
+	/// Return a new, empty `PageIndex`.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Read every page from `reader` into a new `PageIndex`.
+	pub fn build<R: Read>(reader: R) -> Result<Self, Error> {
+		let mut index = Self::new();
+		let mut pages = PageReader::new(reader)?;
+		while let Some((offset, page)) = pages.next_page()? {
+			index.add(offset, &page)
+		}
+		index.set_file_size(pages.offset());
+		Ok(index)
+	}
+
+	/// Index every page in `bytes` in place, such as a memory
+	/// mapped file, without copying the pages. See [scan_pages].
+	pub fn scan(bytes: &[u8]) -> Self {
+		let mut index = Self::new();
+		for (offset, page) in scan_pages(bytes) {
+			index.add_ref(offset, &page)
+		}
+		index.set_file_size(bytes.len() as u64);
+		index
+	}
+
+	/// Add a page found at `offset`.
+	/// 
+	/// The size of the source file is taken to end with the page,
+	/// unless it's set with [set_file_size](PageIndex::set_file_size).
+	pub fn add(&mut self, offset: u64, page: &Page) {
+		self.push(page.crc_checksum(), PageEntry::new(offset, page.header()))
+	}
+
+	/// Add a borrowed page found at `offset`, see [add](PageIndex::add).
+	pub fn add_ref(&mut self, offset: u64, page: &PageRef) {
+		self.push(page.crc_checksum(), PageEntry::new(offset, page.header()))
+	}
+
+	fn push(&mut self, checksum: u32, entry: PageEntry) {
+		if self.entries.is_empty() {
+			self.first_checksum = checksum
+		}
+		self.file_size = self.file_size.max(entry.offset + entry.length as u64);
+		self.insert(entry)
+	}
+
+	/// Add an entry without changing the recorded source file.
+	fn insert(&mut self, entry: PageEntry) {
+		if entry.absgp != u64::MAX {
+			let granules = self.granules.entry(entry.serial).or_default();
+			// After every page with the same granule position, which
+			// is at the end unless granule positions go backward
+			let at = granules.partition_point(|(absgp, _)| *absgp <= entry.absgp);
+			granules.insert(at, (entry.absgp, self.entries.len()))
+		}
+		self.entries.push(entry)
+	}
+
+	/// Set the size of the source file, such as when it has bytes
+	/// after the last page.
+	pub fn set_file_size(&mut self, file_size: u64) {
+		self.file_size = file_size
+	}
+
+	/// Return the size of the source file.
+	pub fn file_size(&self) -> u64 {
+		self.file_size
+	}
+
+	/// Return every page, in the order they were added.
+	pub fn entries(&self) -> &[PageEntry] {
+		&self.entries
+	}
+
+	/// Return the page of the logical stream with the serial number
+	/// `serial` with the lowest granule position of at least `absgp`,
+	/// the first one if there are several.
+	/// 
+	/// The packet `absgp` falls in finishes on this page, but it
+	/// may begin on an earlier one.
+	pub fn find_granule(&self, serial: i32, absgp: u64) -> Option<&PageEntry> {
+		let granules = self.granules.get(&serial)?;
+		let (_, entry) = granules.get(granules.partition_point(|(granule, _)| *granule < absgp))?;
+		Some(&self.entries[*entry])
+	}
+
+	/// Write the index to `writer` as a sidecar file.
+	/// 
+	/// Every number is little endian. The file starts with `OgIx`,
+	/// the version as 16 bits, the size of the source file as 64
+	/// bits, the checksum of its first page as 32 bits and the
+	/// number of pages as 64 bits. Every page follows as 30 bytes:
+	/// the offset, length, serial number, sequence number and
+	/// granule position, then the flags and packet count.
+	pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+		let mut bytes = Vec::with_capacity(26 + self.entries.len() * PageEntry::SIZE);
+		bytes.extend_from_slice(MAGIC);
+		bytes.extend_from_slice(&VERSION.to_le_bytes());
+		bytes.extend_from_slice(&self.file_size.to_le_bytes());
+		bytes.extend_from_slice(&self.first_checksum.to_le_bytes());
+		bytes.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+		for entry in &self.entries {
+			entry.write_to(&mut bytes)
+		}
+		writer.write_all(&bytes)?;
+		writer.flush()?;
+		Ok(())
+	}
+
+	/// Read an index from a sidecar file, without checking it
+	/// against its source file.
+	/// 
+	/// Returns [IndexError::WrongLength] if the file doesn't hold
+	/// as many pages as it says, and [IndexError::PageOutsideFile]
+	/// if a page ends after the source file.
+	pub fn read_from<R: Read>(mut reader: R) -> Result<Self, Error> {
+		let mut header = [0; 26];
+		reader.read_exact(&mut header)?;
+		if &header[0..4] != MAGIC { return Err(IndexError::NotAnIndex.into()) }
+		let version = u16::from_le_bytes(header[4..6].try_into().unwrap());
+		if version != VERSION { return Err(IndexError::UnsupportedVersion(version).into()) }
+
+		let file_size = u64::from_le_bytes(header[6..14].try_into().unwrap());
+		let first_checksum = u32::from_le_bytes(header[14..18].try_into().unwrap());
+		let count = u64::from_le_bytes(header[18..26].try_into().unwrap());
+		let mut bytes = vec![];
+		reader.read_to_end(&mut bytes)?;
+		if count.checked_mul(PageEntry::SIZE as u64) != Some(bytes.len() as u64) {
+			return Err(IndexError::WrongLength.into())
+		}
+
+		let mut index = Self { file_size, first_checksum, ..Self::default() };
+		for entry in bytes.chunks_exact(PageEntry::SIZE) {
+			let entry = PageEntry::read_from(entry.try_into().unwrap());
+			if entry.offset.checked_add(entry.length as u64).filter(|&end| end <= file_size).is_none() {
+				return Err(IndexError::PageOutsideFile.into())
+			}
+			index.insert(entry)
+		}
+		Ok(index)
+	}
+
+	/// Read an index from a sidecar file and check that it belongs
+	/// to `source`.
+	/// 
+	/// Returns [IndexError::Stale] if the size of `source` or the
+	/// checksum of its first page isn't what the index recorded.
+	pub fn load<R: Read, F: Read + Seek>(sidecar: R, mut source: F) -> Result<Self, Error> {
+		let index = Self::read_from(sidecar)?;
+
+		let file_size = source.seek(SeekFrom::End(0))?;
+		source.seek(SeekFrom::Start(0))?;
+		let first_checksum = match PageReader::new(&mut source)?.next_page()? {
+			Some((_, page)) => page.crc_checksum(),
+			None => 0
+		};
+		if file_size != index.file_size || first_checksum != index.first_checksum {
+			return Err(IndexError::Stale.into())
+		}
+
+		Ok(index)
+	}
+}
+
+/// An error returned while reading a [PageIndex].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexError {
+	/// The sidecar file doesn't start like one.
+	NotAnIndex,
+	/// The sidecar file has a version that can't be read.
+	UnsupportedVersion (u16),
+	/// The sidecar file doesn't hold as many pages as it says.
+	WrongLength,
+	/// A page in the sidecar file ends after its source file.
+	PageOutsideFile,
+	/// The source file isn't the one the index was built from.
+	Stale
+}
+
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::NotAnIndex => write!(f, "the file is not a page index"),
+			Self::UnsupportedVersion(version) => write!(f, "page index version {} is not supported", version),
+			Self::WrongLength => write!(f, "the page index does not hold as many pages as it says"),
+			Self::PageOutsideFile => write!(f, "a page in the page index ends after its source file"),
+			Self::Stale => write!(f, "the page index does not match its source file")
+		}
+    }
+}
+
+impl std::error::Error for IndexError {}
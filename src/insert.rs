@@ -0,0 +1,178 @@
+use std::{
+	collections::{ HashMap, HashSet },
+	io::{ Read, Seek, SeekFrom, Write }
+};
+
+use crate::{ Codec, Error, Page, PageReader, Timebase };
+
+/// Keeps track of the time of pages in one or more logical streams.
+struct PageTimes {
+	timebases: HashMap<i32, Option<Timebase>>,
+	/// The time of the last page with a known time.
+	last: f64
+}
+
+impl PageTimes {
+	fn new() -> Self {
+		Self { timebases: HashMap::new(), last: 0.0 }
+	}
+
+	/// Return the time a page ends at, in seconds.
+	/// 
+	/// Pages without a time are given the time of the page before.
+	fn time(&mut self, page: &Page) -> f64 {
+		let serial = page.stream_serial();
+		if page.begins_logical_stream() {
+			let timebase = Timebase::from_header(Codec::identify(page.data()), page.data());
+			self.timebases.insert(serial, timebase);
+		}
+
+		if page.absgp() != u64::MAX {
+			if let Some(Some(timebase)) = self.timebases.get(&serial) {
+				self.last = timebase.seconds(page.absgp())
+			}
+		}
+		self.last
+	}
+}
+
+/// Write a page of the logical stream being inserted.
+fn write_new<W: Write>(writer: &mut W, mut page: Page, serial: i32) -> Result<(), Error> {
+	if page.stream_serial() != serial {
+		page.set_stream_serial(serial);
+		page.set_crc_checksum();
+	}
+	writer.write_all(page.header())?;
+	writer.write_all(page.data())?;
+	Ok(())
+}
+
+fn write<W: Write>(writer: &mut W, page: &Page) -> Result<(), Error> {
+	writer.write_all(page.header())?;
+	writer.write_all(page.data())?;
+	Ok(())
+}
+
+/// Add the logical stream in `stream` to the physical stream in
+/// `file`, and write the result to `writer`.
+/// 
+/// None of the logical streams are decoded, only their pages
+/// are moved around:
+/// 
+/// - The beginning of stream page of the new logical stream goes
+///   after the beginning of stream pages in `file`.
+/// - The rest of its header pages go right after that, before
+///   any data pages.
+/// - Its data pages are placed among the pages in `file` by the
+///   time they end at, as found from their granule positions.
+/// 
+/// If the serial number of the new logical stream is used
+/// anywhere in `file`, it is given the next free one. In a
+/// chained physical stream, the new logical stream is added to
+/// the first link.
+/// 
+/// Returns the serial number of the new logical stream.
+pub fn insert_stream<F, R, W>(mut file: F, stream: R, mut writer: W) -> Result<i32, Error>
+where
+	F: Read + Seek,
+	R: Read,
+	W: Write
+{
+	file.seek(SeekFrom::Start(0))?;
+	let mut used: HashSet<i32> = HashSet::new();
+	let mut pages = PageReader::new(&mut file)?;
+	while let Some((_, page)) = pages.next_page()? {
+		used.insert(page.stream_serial());
+	}
+	drop(pages);
+
+	let mut new_pages = PageReader::new(stream)?;
+	let first = match new_pages.next_page()? {
+		Some((_, page)) if page.begins_logical_stream() => page,
+		_ => return Err(InsertError::NoBeginningOfStream.into())
+	};
+	let mut serial = first.stream_serial();
+	while used.contains(&serial) {
+		serial = serial.wrapping_add(1)
+	}
+	let codec = Codec::identify(first.data());
+	let header_packets = codec.header_packets(first.data()).unwrap_or(1);
+	let mut new_times = PageTimes::new();
+	new_times.time(&first);
+
+	// Every beginning of stream page
+	file.seek(SeekFrom::Start(0))?;
+	let mut pages = PageReader::new(&mut file)?;
+	let mut times = PageTimes::new();
+	let mut next = None;
+	while let Some((_, page)) = pages.next_page()? {
+		if !page.begins_logical_stream() {
+			next = Some(page);
+			break
+		}
+		times.time(&page);
+		write(&mut writer, &page)?;
+	}
+
+	// The headers of the new logical stream
+	let mut packets = first.finished_packets() as usize;
+	write_new(&mut writer, first, serial)?;
+	let mut next_new = None;
+	while let Some((_, page)) = new_pages.next_page()? {
+		if packets >= header_packets {
+			next_new = Some(page);
+			break
+		}
+		packets += page.finished_packets() as usize;
+		new_times.time(&page);
+		write_new(&mut writer, page, serial)?;
+	}
+
+	// The rest, by time
+	let mut first_link = true;
+	loop {
+		let take_new = match (&next, &next_new) {
+			(None, None) => break,
+			(None, Some(_)) => true,
+			(Some(_), None) => false,
+			(Some(page), Some(new_page)) => {
+				// Anything left goes before the next link
+				if page.begins_logical_stream() { first_link = false }
+				!first_link || new_times.time(new_page) < times.time(page)
+			}
+		};
+
+		if take_new {
+			let page = next_new.take().unwrap();
+			new_times.time(&page);
+			write_new(&mut writer, page, serial)?;
+			next_new = new_pages.next_page()?.map(|(_, page)| page);
+		} else {
+			let page = next.take().unwrap();
+			times.time(&page);
+			write(&mut writer, &page)?;
+			next = pages.next_page()?.map(|(_, page)| page);
+		}
+	}
+
+	writer.flush()?;
+	Ok(serial)
+}
+
+/// An error returned by [insert_stream].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertError {
+	/// The logical stream to add doesn't start with a beginning
+	/// of stream page.
+	NoBeginningOfStream
+}
+
+impl std::fmt::Display for InsertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::NoBeginningOfStream => write!(f, "the logical stream to add has no beginning of stream page")
+		}
+    }
+}
+
+impl std::error::Error for InsertError {}
@@ -25,7 +25,7 @@
 //! # use ogg_xiph::SyncState;
 //! // The sync state may fail to initialize, so we should match that
 //! let mut sync_state = match SyncState::new() {
-//! 	Err(()) => panic!("initializing sync state failed!"),
+//! 	Err(error) => panic!("initializing sync state failed: {}", error),
 //! 	Ok(sync_state) => sync_state
 //! };
 //! // And now we have a SyncState!
@@ -43,7 +43,6 @@
 //! 	// Successfully gathered page(s)
 //! 	Ok(Some(pages)) => Some(pages),
 //! 	// Not enough data was available to build a page
-//! 	// This might also indicate an internal error
 //! 	Ok(None) => None,
 //! 	// An error occurred
 //! 	Err(page_write_error) => panic!("ogg returned an error: {}", page_write_error)
@@ -55,15 +54,59 @@
 // Forget you, Clippy.
 #![allow(clippy::tabs_in_doc_comments)]
 
+mod append;
+#[cfg(feature = "async")]
+mod async_io;
+mod concat;
+mod cut;
+mod duration;
+mod extract;
+mod index;
+mod insert;
+mod mapping;
 mod packet;
 mod page;
+#[cfg(feature = "codec")]
+mod page_codec;
+mod page_policy;
+mod page_ref;
+mod read_at;
+mod reader;
+mod repaginate;
+mod repair;
+mod split;
 mod stream_state;
 mod sync_state;
+mod timestamp;
+mod writer;
 
-pub use packet::{ Packet, PacketInitError };
+pub use append::{ AppendError, append };
+#[cfg(feature = "async")]
+pub use async_io::{ AsyncPageReader, AsyncPacketReader, AsyncPageWriter };
+pub use concat::{ ConcatError, Reserial, concat };
+pub use cut::{ CutError, CutSummary, cut };
+pub use duration::{ FileDuration, StreamDuration, duration };
+pub use extract::{ ExtractError, Selection, drop_streams, extract };
+pub use index::{ IndexError, PageEntry, PageIndex };
+pub use insert::{ InsertError, insert_stream };
+pub use mapping::{ Codec, Comments, Timebase };
+pub use packet::{ Packet, PacketRef, PacketInitError };
 pub use page::{ Page, InvalidPage, InvalidPageHeader };
-pub use stream_state::{ Stream, PageInError, PacketOutError };
-pub use sync_state::{ SyncState, PageWriteError };
+#[cfg(feature = "codec")]
+pub use page_codec::PageCodec;
+pub use page_policy::{ PagePolicy, PendingPackets, LowLatency, MinimalOverhead, OpusRecommended };
+#[cfg(feature = "mmap")]
+pub use page_ref::MappedFile;
+pub use page_ref::{ PageRef, PageScanner, scan_pages };
+pub use read_at::{ CachedReader, ReadAt, ReadAtCursor };
+pub use reader::{ PageReader, PacketReader, Damage, DamageReport, damage_report };
+pub use repaginate::{ PagePacing, RepaginateSummary, repaginate };
+pub use repair::{ RepairSummary, repair, salvage };
+pub use split::{ Link, LinkStream, split_links };
+pub use stream_state::{ Stream, Packets, Pages, PageInError, PacketOutError, PageOutError };
+pub use sync_state::{ SyncState, SyncPages, PageSeek, PageWriteError };
+pub use timestamp::{ Timestamper, Trims, gapless_trims };
+pub use writer::{ PacketWriter, PacketWriteError };
 
 #[cfg(test)]
 mod tests;
@@ -80,8 +123,203 @@ impl std::fmt::Display for InternalError {
     }
 }
 
+impl std::error::Error for InternalError {}
+
 impl InternalError {
 	fn fmt_str(f: &mut std::fmt::Formatter<'_>, function: &str) -> std::fmt::Result {
 		write!(f, "an internal error occurred while running {}", function)
 	}
 }
+
+/// Any error returned by this crate.
+/// 
+/// Every more specific error converts into this one, so it can
+/// be used with `?` when the exact cause isn't interesting.
+/// It can also be converted into a [std::io::Error] for use
+/// in readers and writers.
+#[derive(Debug)]
+pub enum Error {
+	/// An internal error in ogg.
+	Internal (InternalError),
+	/// A page could not be read.
+	InvalidPage (InvalidPage),
+	/// A page header could not be read.
+	InvalidPageHeader (InvalidPageHeader),
+	/// A packet could not be read.
+	PacketInit (PacketInitError),
+	/// A page could not be added to a [Stream].
+	PageIn (PageInError),
+	/// A packet could not be taken out of a [Stream].
+	PacketOut (PacketOutError),
+	/// A page could not be taken out of a [Stream].
+	PageOut (PageOutError),
+	/// A page could not be taken out of a [SyncState].
+	PageWrite (PageWriteError),
+	/// A packet could not be written by a [PacketWriter].
+	PacketWrite (PacketWriteError),
+	/// A logical stream could not be appended to.
+	Append (AppendError),
+	/// Physical streams could not be joined.
+	Concat (ConcatError),
+	/// A logical stream could not be cut.
+	Cut (CutError),
+	/// A logical stream could not be extracted.
+	Extract (ExtractError),
+	/// A page index could not be read.
+	Index (IndexError),
+	/// A logical stream could not be added.
+	Insert (InsertError),
+	/// Reading or writing the underlying data failed.
+	Io (std::io::Error)
+}
+
+impl Error {
+	/// Check whether this error only means that more data is
+	/// needed before anything else can be returned.
+	pub fn needs_more_data(&self) -> bool {
+		matches!(self,
+			Self::PacketOut(PacketOutError::NeedMoreData)
+			| Self::PageOut(PageOutError::NeedMoreData)
+			| Self::PageWrite(PageWriteError::NeedMoreData)
+		)
+	}
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::Internal(error) => error.fmt(f),
+			Self::InvalidPage(error) => error.fmt(f),
+			Self::InvalidPageHeader(error) => error.fmt(f),
+			Self::PacketInit(error) => error.fmt(f),
+			Self::PageIn(error) => error.fmt(f),
+			Self::PacketOut(error) => error.fmt(f),
+			Self::PageOut(error) => error.fmt(f),
+			Self::PageWrite(error) => error.fmt(f),
+			Self::PacketWrite(error) => error.fmt(f),
+			Self::Append(error) => error.fmt(f),
+			Self::Concat(error) => error.fmt(f),
+			Self::Cut(error) => error.fmt(f),
+			Self::Extract(error) => error.fmt(f),
+			Self::Index(error) => error.fmt(f),
+			Self::Insert(error) => error.fmt(f),
+			Self::Io(error) => error.fmt(f)
+		}
+    }
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		// The wrapped error is already displayed, so skip
+		// straight to its source
+		match self {
+			Self::Internal(error) => error.source(),
+			Self::InvalidPage(error) => error.source(),
+			Self::InvalidPageHeader(error) => error.source(),
+			Self::PacketInit(error) => error.source(),
+			Self::PageIn(error) => error.source(),
+			Self::PacketOut(error) => error.source(),
+			Self::PageOut(error) => error.source(),
+			Self::PageWrite(error) => error.source(),
+			Self::PacketWrite(error) => error.source(),
+			Self::Append(error) => error.source(),
+			Self::Concat(error) => error.source(),
+			Self::Cut(error) => error.source(),
+			Self::Extract(error) => error.source(),
+			Self::Index(error) => error.source(),
+			Self::Insert(error) => error.source(),
+			Self::Io(error) => error.source()
+		}
+	}
+}
+
+impl From<InternalError> for Error {
+	fn from(error: InternalError) -> Self { Self::Internal(error) }
+}
+
+impl From<InvalidPage> for Error {
+	fn from(error: InvalidPage) -> Self { Self::InvalidPage(error) }
+}
+
+impl From<InvalidPageHeader> for Error {
+	fn from(error: InvalidPageHeader) -> Self { Self::InvalidPageHeader(error) }
+}
+
+impl From<PacketInitError> for Error {
+	fn from(error: PacketInitError) -> Self { Self::PacketInit(error) }
+}
+
+impl From<PageInError> for Error {
+	fn from(error: PageInError) -> Self { Self::PageIn(error) }
+}
+
+impl From<PacketOutError> for Error {
+	fn from(error: PacketOutError) -> Self { Self::PacketOut(error) }
+}
+
+impl From<PageOutError> for Error {
+	fn from(error: PageOutError) -> Self { Self::PageOut(error) }
+}
+
+impl From<PageWriteError> for Error {
+	fn from(error: PageWriteError) -> Self { Self::PageWrite(error) }
+}
+
+impl From<PacketWriteError> for Error {
+	fn from(error: PacketWriteError) -> Self { Self::PacketWrite(error) }
+}
+
+impl From<AppendError> for Error {
+	fn from(error: AppendError) -> Self { Self::Append(error) }
+}
+
+impl From<ConcatError> for Error {
+	fn from(error: ConcatError) -> Self { Self::Concat(error) }
+}
+
+impl From<CutError> for Error {
+	fn from(error: CutError) -> Self { Self::Cut(error) }
+}
+
+impl From<ExtractError> for Error {
+	fn from(error: ExtractError) -> Self { Self::Extract(error) }
+}
+
+impl From<IndexError> for Error {
+	fn from(error: IndexError) -> Self { Self::Index(error) }
+}
+
+impl From<InsertError> for Error {
+	fn from(error: InsertError) -> Self { Self::Insert(error) }
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Self { Self::Io(error) }
+}
+
+impl From<Error> for std::io::Error {
+	fn from(error: Error) -> Self {
+		use std::io::ErrorKind;
+
+		if let Error::Io(error) = error { return error }
+
+		let kind = match &error {
+			_ if error.needs_more_data() => ErrorKind::UnexpectedEof,
+			Error::Internal(_)
+			| Error::PacketOut(PacketOutError::InternalError)
+			| Error::PageOut(PageOutError::InternalError)
+			| Error::PageWrite(PageWriteError::InternalError)
+			| Error::PageIn(PageInError::InternalError(_)) => ErrorKind::Other,
+			Error::PacketOut(PacketOutError::NoPages)
+			| Error::PageIn(PageInError::WrongSerial(_))
+			| Error::PacketWrite(PacketWriteError::HeaderAfterData)
+			| Error::Append(AppendError::StreamNotFound(_))
+			| Error::Append(AppendError::StreamNotAtEnd(_))
+			| Error::Extract(ExtractError::NotFound(_))
+			| Error::Cut(CutError::OutOfRange) => ErrorKind::InvalidInput,
+			_ => ErrorKind::InvalidData
+		};
+
+		std::io::Error::new(kind, error)
+	}
+}
@@ -0,0 +1,438 @@
+/// A codec carried in a logical stream, as found in its first packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Codec {
+	Vorbis,
+	Opus,
+	Theora,
+	Flac,
+	Speex,
+	/// Uncompressed audio, as in the OggPCM mapping.
+	Pcm,
+	Skeleton,
+	/// A codec this crate doesn't know about.
+	Unknown
+}
+
+impl Codec {
+	/// Identify the codec from the first packet of a logical stream.
+	pub fn identify(packet: &[u8]) -> Self {
+		if packet.starts_with(b"\x01vorbis") { Self::Vorbis }
+		else if packet.starts_with(b"OpusHead") { Self::Opus }
+		else if packet.starts_with(b"\x80theora") { Self::Theora }
+		else if packet.starts_with(b"\x7fFLAC") { Self::Flac }
+		else if packet.starts_with(b"Speex   ") { Self::Speex }
+		else if packet.starts_with(b"PCM     ") { Self::Pcm }
+		else if packet.starts_with(b"fishead\0") { Self::Skeleton }
+		else { Self::Unknown }
+	}
+
+	/// Return the number of header packets at the start of the
+	/// logical stream, including the first packet `packet`.
+	/// 
+	/// Returns `None` if this isn't known for the codec, or if
+	/// the first packet doesn't say.
+	pub fn header_packets(&self, packet: &[u8]) -> Option<usize> {
+		match self {
+			Self::Vorbis | Self::Theora => Some(3),
+			Self::Opus => Some(2),
+			Self::Flac => match u16::from_be_bytes(packet.get(7..9)?.try_into().unwrap()) {
+				0 => None,
+				headers => Some(1 + headers as usize)
+			},
+			Self::Speex => Some(2 + u32::from_le_bytes(packet.get(68..72)?.try_into().unwrap()) as usize),
+			Self::Pcm => Some(2 + u32::from_be_bytes(packet.get(24..28)?.try_into().unwrap()) as usize),
+			Self::Skeleton | Self::Unknown => None
+		}
+	}
+}
+
+impl std::fmt::Display for Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+			Self::Vorbis => "Vorbis",
+			Self::Opus => "Opus",
+			Self::Theora => "Theora",
+			Self::Flac => "FLAC",
+			Self::Speex => "Speex",
+			Self::Pcm => "PCM",
+			Self::Skeleton => "Skeleton",
+			Self::Unknown => "unknown"
+		})
+    }
+}
+
+/// The comments of a logical stream, in the format shared by
+/// Vorbis, Opus, Theora, FLAC, Speex and PCM.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Comments {
+	/// The name of the encoder.
+	pub vendor: String,
+	/// Every comment as a field name and value, in order.
+	pub fields: Vec<(String, String)>
+}
+
+impl Comments {
+	/// Read the comments from a header `packet` of a logical
+	/// stream carrying `codec`.
+	/// 
+	/// Returns `None` if the packet isn't a comment header.
+	pub fn parse(codec: Codec, packet: &[u8]) -> Option<Self> {
+		let comments = match codec {
+			Codec::Vorbis => packet.strip_prefix(b"\x03vorbis")?,
+			Codec::Opus => packet.strip_prefix(b"OpusTags")?,
+			Codec::Theora => packet.strip_prefix(b"\x81theora")?,
+			// A metadata block header with the type of a
+			// Vorbis comment block
+			Codec::Flac if packet.first()? & 0x7F == 4 => packet.get(4..)?,
+			Codec::Speex | Codec::Pcm => packet,
+			_ => return None
+		};
+
+		let mut reader = CommentReader { data: comments };
+		let vendor = reader.string()?;
+		let count = reader.length()?;
+		let mut fields = vec![];
+		for _ in 0..count {
+			let comment = reader.string()?;
+			match comment.split_once('=') {
+				Some((name, value)) => fields.push((name.to_string(), value.to_string())),
+				None => fields.push((comment, String::new()))
+			}
+		}
+
+		Some(Self { vendor, fields })
+	}
+
+	/// Return the value of the first field called `name`,
+	/// ignoring case.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.fields.iter()
+			.find(|(field, _)| field.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+}
+
+/// Reads the parts of a comment header.
+struct CommentReader<'a> {
+	data: &'a [u8]
+}
+
+impl CommentReader<'_> {
+	fn length(&mut self) -> Option<usize> {
+		let (length, rest) = self.data.split_first_chunk::<4>()?;
+		self.data = rest;
+		Some(u32::from_le_bytes(*length) as usize)
+	}
+
+	fn string(&mut self) -> Option<String> {
+		let length = self.length()?;
+		if length > self.data.len() { return None }
+		let (string, rest) = self.data.split_at(length);
+		self.data = rest;
+		Some(String::from_utf8_lossy(string).into_owned())
+	}
+}
+
+/// How the granule positions of a logical stream relate to time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timebase {
+	/// The number of granule units in `denominator` seconds,
+	/// such as the sample rate for audio.
+	pub numerator: u64,
+	pub denominator: u64,
+	/// The number of low bits of the granule position that
+	/// count frames since the last keyframe. The high bits
+	/// count frames up to that keyframe.
+	pub keyframe_shift: u8,
+	/// The number of granule units at the start of the logical
+	/// stream that aren't played, such as Opus pre-skip.
+	pub pre_skip: u64
+}
+
+impl Timebase {
+	/// Read the timebase from the first packet of a logical
+	/// stream carrying `codec`.
+	/// 
+	/// Returns `None` if the codec has no timebase or the
+	/// packet is too short.
+	pub fn from_header(codec: Codec, packet: &[u8]) -> Option<Self> {
+		let audio = |rate: u32| Some(Self { numerator: rate as u64, denominator: 1, keyframe_shift: 0, pre_skip: 0 });
+		let le_u32 = |at: usize| packet.get(at..at + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+		let be_u32 = |at: usize| packet.get(at..at + 4).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()));
+
+		let timebase = match codec {
+			// Opus always counts at 48 kHz
+			Codec::Opus => Self {
+				numerator: 48000,
+				denominator: 1,
+				keyframe_shift: 0,
+				pre_skip: u16::from_le_bytes(packet.get(10..12)?.try_into().unwrap()) as u64
+			},
+			Codec::Vorbis => audio(le_u32(12)?)?,
+			Codec::Speex => audio(le_u32(36)?)?,
+			Codec::Pcm => audio(be_u32(16)?)?,
+			// The sample rate is 20 bits into STREAMINFO
+			Codec::Flac => {
+				let bytes = packet.get(27..30)?;
+				audio(((bytes[0] as u32) << 12) | ((bytes[1] as u32) << 4) | (bytes[2] as u32 >> 4))?
+			},
+			Codec::Theora => {
+				let bytes = packet.get(40..42)?;
+				Self {
+					numerator: be_u32(22)? as u64,
+					denominator: be_u32(26)? as u64,
+					keyframe_shift: ((bytes[0] & 0x03) << 3) | (bytes[1] >> 5),
+					pre_skip: 0
+				}
+			},
+			Codec::Skeleton | Codec::Unknown => return None
+		};
+
+		if timebase.numerator == 0 || timebase.denominator == 0 { return None }
+		Some(timebase)
+	}
+
+	/// Return the number of granule units up to `absgp`, such
+	/// as samples for audio or frames for video.
+	pub fn units(&self, absgp: u64) -> u64 {
+		if self.keyframe_shift == 0 { return absgp }
+		let shift = self.keyframe_shift.min(63);
+		(absgp >> shift) + (absgp & ((1 << shift) - 1))
+	}
+
+	/// Return the time of `absgp` in seconds, after the units
+	/// that aren't played.
+	pub fn seconds(&self, absgp: u64) -> f64 {
+		self.seconds_between(0, absgp)
+	}
+
+	/// Return the time from `start` to `end` in seconds, after
+	/// the units that aren't played.
+	pub fn seconds_between(&self, start: u64, end: u64) -> f64 {
+		let units = self.units(end).saturating_sub(self.units(start));
+		units.saturating_sub(self.pre_skip) as f64 * self.denominator as f64 / self.numerator as f64
+	}
+}
+
+/// Return the number of samples at 48 kHz in an Opus packet,
+/// as found from its table of contents.
+/// 
+/// Returns `None` if the packet is invalid.
+pub(crate) fn opus_packet_samples(packet: &[u8]) -> Option<u64> {
+	let toc = *packet.first()?;
+	let config = toc >> 3;
+	let frame = match config {
+		// SILK
+		0..=11 => [480, 960, 1920, 2880][config as usize % 4],
+		// Hybrid
+		12..=15 => [480, 960][config as usize % 2],
+		// CELT
+		_ => [120, 240, 480, 960][config as usize % 4]
+	};
+	let frames = match toc & 0x03 {
+		0 => 1,
+		1 | 2 => 2,
+		_ => (*packet.get(1)? & 0x3F) as u64
+	};
+
+	let samples = frame * frames;
+	// A packet can't be longer than 120 ms
+	if frames == 0 || samples > 5760 { return None }
+	Some(samples)
+}
+
+/// Works out how many granule units the data packets of a
+/// logical stream last.
+pub(crate) struct PacketDurations {
+	kind: Durations
+}
+
+enum Durations {
+	Opus,
+	Vorbis {
+		/// The short and long block sizes.
+		blocksizes: [u64; 2],
+		/// Whether every mode uses long blocks.
+		modes: Vec<bool>,
+		/// The block size of the last packet.
+		previous: Option<u64>
+	},
+	Flac,
+	/// Every packet lasts the same.
+	Fixed (u64),
+	/// Every packet lasts its size divided by this.
+	Pcm (u64),
+	Unknown
+}
+
+impl PacketDurations {
+	/// Start with the first header packet of a logical stream
+	/// carrying `codec`.
+	pub(crate) fn new(codec: Codec, header: &[u8]) -> Self {
+		let le_u32 = |at: usize| header.get(at..at + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as u64);
+		let kind = match codec {
+			Codec::Opus => Durations::Opus,
+			Codec::Vorbis => match header.get(28) {
+				Some(sizes) => Durations::Vorbis {
+					blocksizes: [1 << (sizes & 0x0F), 1 << (sizes >> 4)],
+					modes: vec![],
+					previous: None
+				},
+				None => Durations::Unknown
+			},
+			Codec::Flac => Durations::Flac,
+			// The size of a frame times the frames in a packet
+			Codec::Speex => match (le_u32(56), le_u32(64)) {
+				(Some(size), Some(frames)) if size * frames > 0 => Durations::Fixed(size * frames),
+				_ => Durations::Unknown
+			},
+			Codec::Pcm => {
+				let format = header.get(12..16).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()));
+				let bytes = match format {
+					Some(0x00 | 0x01 | 0x10 | 0x11) => 1,
+					Some(0x02 | 0x03) => 2,
+					Some(0x04 | 0x05) => 3,
+					Some(0x06 | 0x07 | 0x20 | 0x21) => 4,
+					Some(0x22 | 0x23) => 8,
+					_ => 0
+				};
+				match header.get(21) {
+					Some(&channels) if bytes * channels as u64 > 0 => Durations::Pcm(bytes * channels as u64),
+					_ => Durations::Unknown
+				}
+			},
+			_ => Durations::Unknown
+		};
+		Self { kind }
+	}
+
+	/// Read a header packet after the first one.
+	pub(crate) fn header(&mut self, packet: &[u8]) {
+		if let Durations::Vorbis { modes, .. } = &mut self.kind {
+			if packet.starts_with(b"\x05vorbis") {
+				*modes = vorbis_modes(packet).unwrap_or_default()
+			}
+		}
+	}
+
+	/// Return the number of granule units a data packet lasts.
+	/// 
+	/// Packets have to be given in order, as some codecs depend
+	/// on the packet before. Returns `None` if it's not known.
+	pub(crate) fn duration(&mut self, packet: &[u8]) -> Option<u64> {
+		match &mut self.kind {
+			Durations::Opus => opus_packet_samples(packet),
+			Durations::Vorbis { blocksizes, modes, previous } => {
+				let mut bits = BitReader { data: packet, position: 0 };
+				// Not an audio packet
+				if bits.read(1)? != 0 { return None }
+				let mode_bits = u64::BITS - (modes.len().max(1) as u64 - 1).leading_zeros();
+				let long = *modes.get(bits.read(mode_bits)? as usize)?;
+				let size = blocksizes[long as usize];
+				// The first packet only primes the decoder
+				let duration = previous.map(|previous| previous / 4 + size / 4).unwrap_or(0);
+				*previous = Some(size);
+				Some(duration)
+			},
+			Durations::Flac => flac_frame_samples(packet),
+			Durations::Fixed(duration) => Some(*duration),
+			Durations::Pcm(frame) => Some(packet.len() as u64 / *frame),
+			Durations::Unknown => None
+		}
+	}
+}
+
+/// Reads bits from the start of a packet, least significant
+/// first, as Vorbis packs them.
+struct BitReader<'a> {
+	data: &'a [u8],
+	position: usize
+}
+
+impl BitReader<'_> {
+	fn bit(&self, position: usize) -> Option<u64> {
+		Some((*self.data.get(position / 8)? as u64 >> (position % 8)) & 1)
+	}
+
+	fn read(&mut self, bits: u32) -> Option<u64> {
+		let mut value = 0;
+		for bit in 0..bits {
+			value |= self.bit(self.position)? << bit;
+			self.position += 1
+		}
+		Some(value)
+	}
+
+	/// Read a value that ends right before the current position,
+	/// moving backward past it.
+	fn read_back(&mut self, bits: u32) -> Option<u64> {
+		let mut value = 0;
+		for _ in 0..bits {
+			self.position = self.position.checked_sub(1)?;
+			value = (value << 1) | self.bit(self.position)?
+		}
+		Some(value)
+	}
+}
+
+/// Find whether every mode in a Vorbis setup header uses long
+/// blocks.
+/// 
+/// The modes are the last thing in the header, and everything
+/// before them takes a full decoder to read. So they are read
+/// backward from the framing bit at the end, as far as they
+/// look like modes, and the mode count before them is checked.
+fn vorbis_modes(packet: &[u8]) -> Option<Vec<bool>> {
+	let mut bits = BitReader { data: packet, position: packet.len() * 8 };
+	// The framing bit
+	while bits.read_back(1)? == 0 {}
+	let end = bits.position;
+
+	// Every mode is a block flag, a window type and a transform
+	// type that are both 0, and a mapping
+	let mut count = 0;
+	let mut modes = 0;
+	while count < 64 {
+		let mapping = bits.read_back(8)?;
+		if mapping > 63 || bits.read_back(16)? != 0 || bits.read_back(16)? != 0 { break }
+		bits.read_back(1)?;
+		count += 1;
+
+		let before = bits.position;
+		if bits.read_back(6)? + 1 == count { modes = count }
+		bits.position = before
+	}
+	if modes == 0 { return None }
+
+	bits.position = end;
+	let mut long = vec![false; modes as usize];
+	for mode in long.iter_mut().rev() {
+		bits.read_back(40)?;
+		*mode = bits.read_back(1)? == 1
+	}
+	Some(long)
+}
+
+/// Return the number of samples in a FLAC frame, as found
+/// from its header.
+/// 
+/// Returns `None` if the packet isn't a frame.
+fn flac_frame_samples(packet: &[u8]) -> Option<u64> {
+	if *packet.first()? != 0xFF || packet.get(1)? & 0xFE != 0xF8 { return None }
+	match packet.get(2)? >> 4 {
+		0 => None,
+		1 => Some(192),
+		code @ 2..=5 => Some(576 << (code - 2)),
+		// Stored at the end of the header, after the frame or
+		// sample number, which takes as many bytes as its first
+		// byte has leading ones
+		code @ 6..=7 => {
+			let at = 4 + packet.get(4)?.leading_ones().max(1) as usize;
+			match code {
+				6 => Some(*packet.get(at)? as u64 + 1),
+				_ => Some(u16::from_be_bytes(packet.get(at..at + 2)?.try_into().unwrap()) as u64 + 1)
+			}
+		},
+		code => Some(256 << (code - 8))
+	}
+}
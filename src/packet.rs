@@ -1,32 +1,49 @@
 use ogg_next_sys::*;
-use std::os::raw::c_long;
-
-/// A privately owned version of the [ogg_packet] struct.
-#[derive(Clone)]
-pub(crate) struct PrivatePacket {
-	pub data: Vec<u8>,
-	pub beginning_of_stream: bool,
-	pub end_of_stream: bool,
-	pub absgp: u64,
-	pub index: u32
+use std::{
+	marker::PhantomData,
+	os::raw::c_long
+};
+
+/// An owned Ogg packet.
+/// 
+/// Packets returned by a [Stream](crate::Stream) are borrowed
+/// as a [PacketRef] instead. Use [PacketRef::to_packet] to get
+/// an owned copy.
+/// 
+/// A `Packet` only holds Rust data. The [ogg_packet] given to
+/// ogg is built from it when the packet is submitted with
+/// [Stream::packet_in](crate::Stream::packet_in), so changes
+/// made with the setters are always what ends up in the stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Packet {
+	data: Vec<u8>,
+	beginning_of_stream: bool,
+	end_of_stream: bool,
+	absgp: u64,
+	index: u32
 }
 
-impl PrivatePacket {
-	/// Create a new `PrivatePacket`.
+impl Packet {
+	/// Create a new, empty `Packet`.
 	pub fn new() -> Self {
-		Self {
-			data: Vec::with_capacity(1),
-			beginning_of_stream: false,
-			end_of_stream: false,
-			absgp: 0,
-			index: 0
-		}
+		Self::default()
 	}
 
-	/// Get an [ogg_packet] from this `Packet`.
-	pub fn ogg_packet(&mut self) -> ogg_packet {
+	/// Create a new `Packet` with the given data.
+	pub fn with_data(data: Vec<u8>) -> Self {
+		Self { data, ..Self::default() }
+	}
+
+	/// Build an [ogg_packet] pointing to the current data of
+	/// this `Packet`.
+	/// 
+	/// The returned struct borrows from `self` and must not be
+	/// used after this `Packet` is changed or dropped. ogg never
+	/// writes through the packet pointer, it copies the data
+	/// out in `ogg_stream_packetin`.
+	pub(crate) fn ogg_packet(&self) -> ogg_packet {
 		ogg_packet {
-			packet: self.data.as_mut_ptr(),
+			packet: self.data.as_ptr() as *mut u8,
 			bytes: self.data.len().try_into().expect("usize as c_long"),
 			b_o_s: if self.beginning_of_stream { 1 } else { 0 },
 			e_o_s: if self.end_of_stream { 1 } else { 0 },
@@ -34,32 +51,99 @@ impl PrivatePacket {
 			packetno: self.index as i64
 		}
 	}
-}
 
-pub struct Packet {
-	pub(crate) packet: ogg_packet,
-	pub(crate) owned: Option<PrivatePacket>
-}
+	/// Return a reference to the data of this [Packet].
+	pub fn data(&self) -> &[u8] {
+		&self.data
+	}
 
-impl Packet {
-	/// Create a new `Page`.
-	pub fn new() -> Self {
-		let mut owned = PrivatePacket::new();
+	/// Return a mutable reference to the data of this [Packet].
+	pub fn data_mut(&mut self) -> &mut [u8] {
+		&mut self.data
+	}
+
+	/// Set the data of this `Packet`.
+	pub fn set_data(&mut self, data: Vec<u8>) {
+		self.data = data
+	}
+
+	/// Check whether this packet begins a logical stream.
+	pub fn begins_logical_stream(&self) -> bool {
+		self.beginning_of_stream
+	}
+
+	/// Set whether this packet begins a logical stream.
+	/// 
+	/// This is informational only when encoding, ogg always
+	/// marks the first page of a [Stream](crate::Stream) itself.
+	pub fn set_begins_logical_stream(&mut self, begins_logical_stream: bool) {
+		self.beginning_of_stream = begins_logical_stream
+	}
+
+	/// Check whether this packet ends a logical stream.
+	pub fn ends_logical_stream(&self) -> bool {
+		self.end_of_stream
+	}
+
+	/// Set whether this packet ends a logical stream.
+	pub fn set_ends_logical_stream(&mut self, ends_logical_stream: bool) {
+		self.end_of_stream = ends_logical_stream
+	}
+
+	/// Return the absolute granule position of this packet.
+	pub fn absgp(&self) -> u64 {
+		self.absgp
+	}
+
+	/// Set the absolute granule position of this packet.
+	pub fn set_absgp(&mut self, absgp: u64) {
+		self.absgp = absgp
+	}
+
+	/// Return the sequential number of this packet in the stream.
+	pub fn index(&self) -> u32 {
+		self.index
+	}
+
+	/// Set the sequential number of this packet in the stream.
+	/// 
+	/// This is informational only when encoding, ogg numbers
+	/// submitted packets itself.
+	pub fn set_index(&mut self, index: u32) {
+		self.index = index
+	}
+}
 
-		Self { packet: owned.ogg_packet(), owned: Some(owned) }
+impl From<PacketRef<'_>> for Packet {
+	fn from(packet: PacketRef<'_>) -> Self {
+		packet.to_packet()
 	}
+}
 
-	/// Try to create a [Packet] from an [ogg_packet].
+/// A packet borrowed from a [Stream](crate::Stream).
+/// 
+/// The data of this packet is owned by ogg and is only valid
+/// until the next call on the `Stream` it came from, so a
+/// `PacketRef` can't outlive that borrow and can't be mutated.
+pub struct PacketRef<'a> {
+	packet: ogg_packet,
+	_stream: PhantomData<&'a mut ()>
+}
+
+impl<'a> PacketRef<'a> {
+	/// Try to create a [PacketRef] from an [ogg_packet].
 	/// 
 	/// Will fail if `bytes` can't be read as `usize` or
 	/// if `b_o_s` or `e_o_s` are any value other than
 	/// `0` or `1`.
 	/// 
-	/// This function is `unsafe` because the underlying
-	/// `ogg_packet` contains raw pointers.
+	/// # Safety
+	/// 
+	/// The pointers in `packet` must stay valid for the
+	/// lifetime `'a`.
 	pub unsafe fn try_from(packet: ogg_packet) -> Result<Self, PacketInitError> {
 		// println!("packet details: b_o_s={}, e_o_s={}", packet.b_o_s, packet.e_o_s);
-        if let Err(usize_error) = usize::try_from(packet.bytes) {
+		if let Err(usize_error) = usize::try_from(packet.bytes) {
 			return Err(PacketInitError::Usize(usize_error))
 		}
 
@@ -75,123 +159,49 @@ impl Packet {
 			return Err(PacketInitError::InvalidEndOfStream(packet.e_o_s))
 		}
 
-		Ok(Packet { packet, owned: None })
-    }
-
-	/// Return a reference to the data of this [Packet].
-	pub fn data(&self) -> &[u8] {
-		match &self.owned {
-			None => unsafe { core::slice::from_raw_parts(self.packet.packet, self.packet.bytes as usize) },
-			Some(owned_packet) => &owned_packet.data
-		}
-	}
-
-	/// Return a reference to the data of this [Packet].
-	pub fn data_mut(&mut self) -> &mut [u8] {
-		match &mut self.owned {
-			None => panic!("mutating a Packet which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned_packet) => &mut owned_packet.data
-		}
+		Ok(PacketRef { packet, _stream: PhantomData })
 	}
 
-	/// Set the data of this `Packet`.
-	pub fn set_data(&mut self, data: Vec<u8>) {
-		match &mut self.owned {
-			None => panic!("mutating a Packet which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned) => {
-				if data.capacity() == 0 {
-					owned.data = Vec::with_capacity(1)
-				} else {
-					owned.data = data;
-				}
-			}
+	/// Return a reference to the data of this [PacketRef].
+	pub fn data(&self) -> &'a [u8] {
+		if self.packet.bytes == 0 {
+			return &[]
 		}
+		unsafe { core::slice::from_raw_parts(self.packet.packet, self.packet.bytes as usize) }
 	}
 
 	/// Check whether this packet begins a logical stream.
 	pub fn begins_logical_stream(&self) -> bool {
-		match &self.owned {
-			None => self.packet.b_o_s != 0,
-			Some(owned_packet) => owned_packet.beginning_of_stream
-		}
-	}
-
-	/// Set whether this packet begins a logical stream.
-	pub fn set_begins_local_stream(&mut self, begins_logical_stream: bool) {
-		match self.owned.as_mut() {
-			None => panic!("mutating a Packet which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned_packet) => owned_packet.beginning_of_stream = begins_logical_stream
-		}
+		self.packet.b_o_s != 0
 	}
 
 	/// Check whether this packet ends a logical stream.
 	pub fn ends_logical_stream(&self) -> bool {
-		match &self.owned {
-			None => self.packet.e_o_s != 0,
-			Some(owned_packet) => owned_packet.end_of_stream
-		}
-	}
-
-	/// Set whether this packet ends a logical stream.
-	pub fn set_ends_local_stream(&mut self, ends_logical_stream: bool) {
-		match self.owned.as_mut() {
-			None => panic!("mutating a Packet which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned_packet) => owned_packet.end_of_stream = ends_logical_stream
-		}
+		self.packet.e_o_s != 0
 	}
 
 	/// Return the absolute granule position of this packet.
 	pub fn absgp(&self) -> u64 {
-		match &self.owned {
-			None => self.packet.granulepos as u64,
-			Some(owned_packet) => owned_packet.absgp
-		}
-	}
-
-	/// Set the absolute granule position of this packet.
-	pub fn set_absgp(&mut self, absgp: u64) {
-		match self.owned.as_mut() {
-			None => panic!("mutating a Packet which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned_packet) => owned_packet.absgp = absgp
-		}
+		self.packet.granulepos as u64
 	}
 
 	/// Return the sequential number of this packet in the stream.
 	pub fn index(&self) -> u32 {
-		match &self.owned {
-			None => self.packet.packetno as u32,
-			Some(owned_packet) => owned_packet.index
-		}
+		self.packet.packetno as u32
 	}
 
-	/// Set the sequential number of this packet in the stream.
-	pub fn set_index(&mut self, index: u32) {
-		match self.owned.as_mut() {
-			None => panic!("mutating a Packet which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned_packet) => owned_packet.index = index
+	/// Copy this packet into an owned [Packet].
+	pub fn to_packet(&self) -> Packet {
+		Packet {
+			data: self.data().to_vec(),
+			beginning_of_stream: self.begins_logical_stream(),
+			end_of_stream: self.ends_logical_stream(),
+			absgp: self.absgp(),
+			index: self.index()
 		}
 	}
 }
 
-impl Clone for Packet {
-    fn clone(&self) -> Self {
-		if let Some(owned) = &self.owned {
-			let mut owned = owned.clone();
-			Self { packet: owned.ogg_packet(), owned: Some(owned) }
-		} else {
-			let mut owned = PrivatePacket {
-				data: self.data().to_vec(),
-				beginning_of_stream: self.begins_logical_stream(),
-				end_of_stream: self.ends_logical_stream(),
-				absgp: self.absgp(),
-				index: self.index()
-			};
-			let packet = owned.ogg_packet();
-	        Self { packet, owned: Some(owned) }
-		}
-    }
-}
-
 /// Error while creating or verifying the [Packet] struct.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum PacketInitError {
@@ -208,10 +218,20 @@ pub enum PacketInitError {
 impl std::fmt::Display for PacketInitError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-			Self::Usize(usize_error) => write!(f, "couldn't read usize: {}", usize_error),
-			Self::U8(u8_error) => write!(f, "couldn't read u8: {}", u8_error),
+			Self::Usize(_) => write!(f, "couldn't read usize"),
+			Self::U8(_) => write!(f, "couldn't read u8"),
 			Self::InvalidBeginningOfStream(i) => write!(f, "invalid beginning of stream flag: {} (should be 0 or 256)", i),
 			Self::InvalidEndOfStream(i) => write!(f, "invalid end of stream flag: {} (should be 0 or 512)", i)
 		}
     }
 }
+
+impl std::error::Error for PacketInitError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Usize(usize_error) => Some(usize_error),
+			Self::U8(u8_error) => Some(u8_error),
+			_ => None
+		}
+	}
+}
@@ -7,8 +7,12 @@ pub const HEADER_GRANULE_POSITION: usize = 6;
 pub const HEADER_PAGE_SERIAL_NUMBER: usize = 14;
 pub const HEADER_SEQUENCE_NUMBER: usize = 18;
 pub const HEADER_CHECKSUM: usize = 22;
-// pub const HEADER_SEGMENTS: usize = 26;
-pub const HEADER_SIZE_MIN: usize = 28;
+pub const HEADER_SEGMENTS: usize = 26;
+pub const HEADER_SIZE_MIN: usize = 27;
+
+pub const HEADER_TYPE_CONTINUED: u8 = 1;
+pub const HEADER_TYPE_BEGINNING: u8 = 2;
+pub const HEADER_TYPE_END: u8 = 4;
 
 /// A privately owned version of the [ogg_page] struct.
 #[derive(Clone)]
@@ -40,10 +44,10 @@ impl PrivatePage {
 pub struct Page {
 	/// The underlying page struct.
 	/// 
-	/// This might contain pointers which point to data owned
-	/// by ogg.
+	/// This only ever points into `owned`, and is updated
+	/// whenever it is handed to ogg.
 	pub(crate) page: ogg_page,
-	pub(crate) owned: Option<PrivatePage>
+	pub(crate) owned: PrivatePage
 }
 
 impl Page {
@@ -51,66 +55,74 @@ impl Page {
 	pub fn new() -> Self {
 		let mut owned = PrivatePage::new();
 
-		Self { page: owned.ogg_page(), owned: Some(owned) }
+		Self { page: owned.ogg_page(), owned }
+	}
+
+	/// Create a new `Page` from its raw header and data.
+	/// 
+	/// Fails if the header is invalid or doesn't describe
+	/// exactly `data`.
+	pub fn from_parts(header: Vec<u8>, data: Vec<u8>) -> Result<Self, InvalidPageHeader> {
+		let mut page = Self::new();
+		page.set_header(header)?;
+		page.set_data(data);
+
+		let segments = page.header()[HEADER_SEGMENTS] as usize;
+		if page.header().len() != HEADER_SEGMENTS + 1 + segments { return Err(InvalidPageHeader::BadLength) }
+		if page.lacing_values().iter().map(|value| *value as usize).sum::<usize>() != page.data().len() {
+			return Err(InvalidPageHeader::BadLength)
+		}
+
+		Ok(page)
 	}
 
 	/// Try to create a [Page] from an [ogg_page].
 	/// 
+	/// The header and data are copied, so the `Page` stays
+	/// valid after ogg reuses or frees its buffers.
+	/// 
 	/// Will fail if `body_len` or `header_len` can't be read as
 	/// [usize].
 	/// 
+	/// # Safety
+	/// 
 	/// This function is `unsafe` because the underlying
-	/// `ogg_page` contains raw pointers.
+	/// `ogg_page` contains raw pointers. They must point to
+	/// valid data of the given lengths during the call.
 	pub unsafe fn try_from(page: ogg_page) -> Result<Self, InvalidPage> {
-		if let Err(usize_error) = usize::try_from(page.body_len) { return Err(InvalidPage::BadPointer(usize_error)) };
-		if let Err(usize_error) = usize::try_from(page.header_len) { return Err(InvalidPage::BadPointer(usize_error)) };
+		let body_len = match usize::try_from(page.body_len) {
+			Err(usize_error) => return Err(InvalidPage::BadPointer(usize_error)),
+			Ok(body_len) => body_len
+		};
+		let header_len = match usize::try_from(page.header_len) {
+			Err(usize_error) => return Err(InvalidPage::BadPointer(usize_error)),
+			Ok(header_len) => header_len
+		};
 
-		let page = Self { page, owned: None };
-		if let Err(header_error) = validate_header(page.header()) { return Err(InvalidPage::InvalidHeader(header_error)) };
-		
-		Ok(page)
-	}
+		let mut owned = PrivatePage::new();
+		if header_len > 0 { owned.header = unsafe { core::slice::from_raw_parts(page.header, header_len) }.to_vec() }
+		if body_len > 0 { owned.body = unsafe { core::slice::from_raw_parts(page.body, body_len) }.to_vec() }
+		if let Err(header_error) = validate_header(&owned.header) { return Err(InvalidPage::InvalidHeader(header_error)) };
 
-	unsafe fn length_from_ogg_page(&mut self) {
-		match &mut self.owned {
-			None => {},
-			Some(owned) => {
-				let header_len = self.page.header_len.try_into().expect("c_long as usize");
-				let body_len = self.page.body_len.try_into().expect("c_long as usize");
-				assert!(owned.header.capacity() >= header_len);
-				owned.header.set_len(header_len);
-				assert!(owned.body.capacity() >= body_len);
-				owned.body.set_len(body_len)
-			}
-		}
+		Ok(Self { page: owned.ogg_page(), owned })
 	}
 
 	/// Set the data of this `Page`.
 	pub fn set_data(&mut self, data: Vec<u8>) {
-		match &mut self.owned {
-			None => panic!("mutating a Page which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned) => {
-				if data.capacity() == 0 {
-					owned.body = Vec::with_capacity(1)
-				} else {
-					owned.body = data;
-				}
-			}
+		if data.capacity() == 0 {
+			self.owned.body = Vec::with_capacity(1)
+		} else {
+			self.owned.body = data;
 		}
 	}
 
 	/// Set the data of this `Page`.
 	pub fn set_header(&mut self, header: Vec<u8>) -> Result<(), InvalidPageHeader> {
-		match &mut self.owned {
-			None => panic!("mutating a Page which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned) => {
-				validate_header(&header)?;
-				if header.capacity() == 0 {
-					owned.header = Vec::with_capacity(1)
-				} else {
-					owned.header = header;
-				}
-			}
+		validate_header(&header)?;
+		if header.capacity() == 0 {
+			self.owned.header = Vec::with_capacity(1)
+		} else {
+			self.owned.header = header;
 		}
 
 		Ok(())
@@ -118,49 +130,36 @@ impl Page {
 
 	/// Get an [ogg_page] from this `Page`.
 	pub fn ogg_page(&mut self) -> &mut ogg_page {
-		if let Some(owned) = &mut self.owned {
-			// Otherwise put pointers into the struct
-			self.page = owned.ogg_page();
+		// The data may have moved since the last call
+		self.page = self.owned.ogg_page();
 
-			&mut self.page
-		} else {
-			// Pages from a `Stream` will be returned here
-			&mut self.page
-		}
+		&mut self.page
 	}
 
 	/// Return a reference to the data for this `Page`.
 	pub fn data(&self) -> &[u8] {
-		match &self.owned {
-			None => unsafe { core::slice::from_raw_parts(self.page.body, self.page.body_len as usize) },
-			Some(owned_page) => &owned_page.body
-		}
+		&self.owned.body
 	}
 
 	/// Return a mutable reference to the data for this `Page`.
 	pub fn data_mut(&mut self) -> &mut [u8] {
-		match &mut self.owned {
-			None => panic!("mutating a Page which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned_page) => &mut owned_page.body
-		}
-		
+		&mut self.owned.body
 	}
 
 	/// Return a reference to the raw header for this `Page`.
 	pub fn header(&self) -> &[u8] {
-		match &self.owned {
-			None => unsafe { core::slice::from_raw_parts(self.page.header, self.page.header_len as usize) },
-			Some(owned_page) => &owned_page.header
-		}
+		&self.owned.header
 	}
 
 	/// Return a mutable reference to the raw header for this `Page`.
 	pub fn header_mut(&mut self) -> &mut [u8] {
-		match &mut self.owned {
-			None => panic!("mutating a Page which is owned by ogg is illegal\nthis is a bug!"),
-			Some(owned_page) => &mut owned_page.header
-		}
-		
+		&mut self.owned.header
+	}
+
+	/// Return the size of this `Page` in bytes, including
+	/// the header.
+	pub fn size(&self) -> usize {
+		self.header().len() + self.data().len()
 	}
 
 	/// Returns the `Page` version.
@@ -184,7 +183,13 @@ impl Page {
 	/// Check whether this `Page` contains packet data that continues
 	/// from the last `Page`.
 	pub fn continues_packet(&self) -> bool {
-		[1, 3, 7].contains(&self.header_type())
+		self.header_type() & HEADER_TYPE_CONTINUED != 0
+	}
+
+	/// Set whether this `Page` contains packet data that continues
+	/// from the last `Page`.
+	pub fn set_continues_packet(&mut self, continues_packet: bool) {
+		self.set_header_type_flag(HEADER_TYPE_CONTINUED, continues_packet)
 	}
 
 	/// Return the number of packets that completed on this `Page`.
@@ -194,18 +199,67 @@ impl Page {
 	/// happens to begin on a previous page and span to a future
 	/// page, in the case of a packet that spans three or more
 	/// pages, the return value of this method would be 0.
-	pub fn finished_packets(&mut self) -> u8 {
-		unsafe { ogg_page_packets(self.ogg_page()) as u8 }
+	pub fn finished_packets(&self) -> u8 {
+		self.lacing_values().iter().filter(|value| **value < 255).count() as u8
+	}
+
+	/// Return the lacing values of this `Page`, one for every
+	/// segment of packet data.
+	/// 
+	/// A value of 255 means the packet goes on in the next
+	/// segment, any other value ends a packet.
+	pub fn lacing_values(&self) -> &[u8] {
+		let header = self.header();
+		let segments = header.get(HEADER_SEGMENTS).copied().unwrap_or(0) as usize;
+		header.get(HEADER_SEGMENTS + 1..HEADER_SEGMENTS + 1 + segments).unwrap_or(&[])
+	}
+
+	/// Replace the packet data of this `Page` along with the
+	/// lacing values that describe it.
+	/// 
+	/// The checksum is not updated, see
+	/// [set_crc_checksum](Page::set_crc_checksum).
+	/// 
+	/// Panics if there are more than 255 lacing values or if
+	/// they don't add up to the length of `data`.
+	pub fn set_segments(&mut self, lacing_values: &[u8], data: Vec<u8>) {
+		assert!(lacing_values.len() <= 255, "a page can have at most 255 segments");
+		assert_eq!(lacing_values.iter().map(|value| *value as usize).sum::<usize>(), data.len(), "lacing values should add up to the data length");
+
+		let mut header = self.header()[..HEADER_SEGMENTS].to_vec();
+		header.push(lacing_values.len() as u8);
+		header.extend_from_slice(lacing_values);
+		self.set_header(header).expect("header should stay valid");
+		self.set_data(data)
 	}
 
 	/// Check whether this page begins a logical stream.
 	pub fn begins_logical_stream(&self) -> bool {
-		[2, 6, 7].contains(&self.header_type())
+		self.header_type() & HEADER_TYPE_BEGINNING != 0
+	}
+
+	/// Set whether this page begins a logical stream.
+	pub fn set_begins_logical_stream(&mut self, begins_logical_stream: bool) {
+		self.set_header_type_flag(HEADER_TYPE_BEGINNING, begins_logical_stream)
 	}
 
 	/// Check whether this `Page` ends a logical [Stream](crate::Stream).
 	pub fn ends_logical_stream(&self) -> bool {
-		[4, 6, 7].contains(&self.header_type())
+		self.header_type() & HEADER_TYPE_END != 0
+	}
+
+	/// Set whether this `Page` ends a logical [Stream](crate::Stream).
+	pub fn set_ends_logical_stream(&mut self, ends_logical_stream: bool) {
+		self.set_header_type_flag(HEADER_TYPE_END, ends_logical_stream)
+	}
+
+	fn set_header_type_flag(&mut self, flag: u8, set: bool) {
+		let header = self.header_mut();
+		if set {
+			header[HEADER_TYPE] |= flag
+		} else {
+			header[HEADER_TYPE] &= !flag
+		}
 	}
 
 	/// Return the absolute granule position of the packet data
@@ -214,12 +268,24 @@ impl Page {
 		u64::from_le_bytes(self.header()[HEADER_GRANULE_POSITION..HEADER_GRANULE_POSITION + 8].try_into().unwrap())
 	}
 
+	/// Set the absolute granule position of the packet data
+	/// at the end of this `Page`.
+	pub fn set_absgp(&mut self, absgp: u64) {
+		self.header_mut()[HEADER_GRANULE_POSITION..HEADER_GRANULE_POSITION + 8].copy_from_slice(&absgp.to_le_bytes())
+	}
+
 	/// Return the serial number of the logical stream that this
 	/// `Page` is associated with.
 	pub fn stream_serial(&self) -> i32 {
 		i32::from_le_bytes(self.header()[HEADER_PAGE_SERIAL_NUMBER..HEADER_PAGE_SERIAL_NUMBER + 4].try_into().unwrap())
 	}
 
+	/// Set the serial number of the logical stream this `Page`
+	/// belongs to.
+	pub fn set_stream_serial(&mut self, serial: i32) {
+		self.header_mut()[HEADER_PAGE_SERIAL_NUMBER..HEADER_PAGE_SERIAL_NUMBER + 4].copy_from_slice(&serial.to_le_bytes())
+	}
+
 	/// Return the sequential number for this `Page`.
 	/// 
 	/// This can be used for ordering pages or detecting pages
@@ -228,6 +294,11 @@ impl Page {
 		u32::from_le_bytes(self.header()[HEADER_SEQUENCE_NUMBER..HEADER_SEQUENCE_NUMBER + 4].try_into().unwrap())
 	}
 
+	/// Set the sequential number for this `Page`.
+	pub fn set_index(&mut self, index: u32) {
+		self.header_mut()[HEADER_SEQUENCE_NUMBER..HEADER_SEQUENCE_NUMBER + 4].copy_from_slice(&index.to_le_bytes())
+	}
+
 	/// Return the CRC checksum of this `Page`.
 	/// 
 	/// This can be used for ordering pages or detecting pages
@@ -236,29 +307,40 @@ impl Page {
 		u32::from_le_bytes(self.header()[HEADER_CHECKSUM..HEADER_CHECKSUM + 4].try_into().unwrap())
 	}
 
-	/// Return the CRC checksum of this `Page`.
+	/// Compute the CRC checksum of this `Page` and write it
+	/// into the header.
 	/// 
-	/// This can be used for ordering pages or detecting pages
-	/// that have been lost.
+	/// This has to be done after changing anything in the page.
 	pub fn set_crc_checksum(&mut self) {
 		unsafe { ogg_page_checksum_set(self.ogg_page()) }
 	}
+
+	/// Check whether the CRC checksum in the header of this
+	/// `Page` matches its contents.
+	pub fn verify_crc_checksum(&self) -> bool {
+		let mut page = self.clone();
+		page.set_crc_checksum();
+		page.crc_checksum() == self.crc_checksum()
+	}
+}
+
+// A `Page` always owns its header and data. The `ogg_page`
+// inside only points into them, and is only read through after
+// `ogg_page` updates it from a unique reference.
+unsafe impl Send for Page {}
+unsafe impl Sync for Page {}
+
+impl Default for Page {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Clone for Page {
-    fn clone(&self) -> Self {
-		if let Some(owned) = &self.owned {
-			let mut owned = owned.clone();
-			Self { page: owned.ogg_page(), owned: Some(owned) }
-		} else {
-			let mut owned = PrivatePage {
-				header: self.header().to_vec(),
-				body: self.data().to_vec()
-			};
-			let page = owned.ogg_page();
-	        Self { page, owned: Some(owned) }
-		}
-    }
+	fn clone(&self) -> Self {
+		let mut owned = self.owned.clone();
+		Self { page: owned.ogg_page(), owned }
+	}
 }
 
 pub fn validate_header(header: &[u8]) -> Result<(), InvalidPageHeader> {
@@ -273,18 +355,28 @@ pub fn validate_header(header: &[u8]) -> Result<(), InvalidPageHeader> {
 pub enum InvalidPage {
 	/// The pointer returned couldn't be read as usize.
 	BadPointer (<usize as TryFrom<c_long>>::Error),
+	/// The page header is invalid.
 	InvalidHeader (InvalidPageHeader)
 }
 
 impl std::fmt::Display for InvalidPage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-			Self::BadPointer (usize_error) => write!(f, "ogg returned an invalid pointer: {}", usize_error),
-			Self::InvalidHeader (header_error) => write!(f, "ogg returned invalid header: {}", header_error)
+			Self::BadPointer (_) => write!(f, "ogg returned an invalid pointer"),
+			Self::InvalidHeader (_) => write!(f, "ogg returned an invalid header")
 		}
     }
 }
 
+impl std::error::Error for InvalidPage {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::BadPointer(usize_error) => Some(usize_error),
+			Self::InvalidHeader(header_error) => Some(header_error)
+		}
+	}
+}
+
 /// Error validating the page header.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InvalidPageHeader {
@@ -293,7 +385,9 @@ pub enum InvalidPageHeader {
 	/// The header version was wrong.
 	BadVersion (u8),
 	/// The header was too short.
-	TooShort
+	TooShort,
+	/// The header doesn't match the length of the page.
+	BadLength
 }
 
 impl std::fmt::Display for InvalidPageHeader {
@@ -301,7 +395,10 @@ impl std::fmt::Display for InvalidPageHeader {
         match self {
 			Self::NoMagicString => write!(f, "header has an invalid magic string (should be 'OggS')"),
 			Self::BadVersion(v) => write!(f, "version number is {} (should be 0)", v),
-			Self::TooShort => write!(f, "page header is too short")
+			Self::TooShort => write!(f, "page header is too short"),
+			Self::BadLength => write!(f, "page header does not match the page length")
 		}
     }
 }
+
+impl std::error::Error for InvalidPageHeader {}
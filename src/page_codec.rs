@@ -0,0 +1,63 @@
+use bytes::{ BufMut, BytesMut };
+use tokio_util::codec::{ Decoder, Encoder };
+
+use crate::{ Error, Page, PageWriteError, SyncState };
+
+/// A [Decoder] and [Encoder] for Ogg [Pages](Page).
+/// 
+/// Use this with [Framed](tokio_util::codec::Framed) to send and
+/// receive pages over any transport, such as a TCP or Unix socket.
+/// 
+/// Decoding goes through a [SyncState], so it captures and
+/// resyncs the same way. Bytes that don't belong to any page
+/// are skipped.
+pub struct PageCodec {
+	sync_state: SyncState
+}
+
+impl PageCodec {
+	/// Return a new `PageCodec`.
+	pub fn new() -> Result<Self, Error> {
+		Ok(Self { sync_state: SyncState::new()? })
+	}
+}
+
+impl Decoder for PageCodec {
+	type Item = Page;
+	type Error = Error;
+
+	fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Page>, Error> {
+		// The sync state keeps its own buffer, so everything can
+		// be handed over to it right away
+		self.sync_state.write(&src.split());
+
+		for page in self.sync_state.pages() {
+			match page {
+				Ok(page) => return Ok(Some(page)),
+				Err(PageWriteError::OutOfSync) => continue,
+				Err(error) => return Err(error.into())
+			}
+		}
+
+		Ok(None)
+	}
+}
+
+impl Encoder<&Page> for PageCodec {
+	type Error = Error;
+
+	fn encode(&mut self, page: &Page, dst: &mut BytesMut) -> Result<(), Error> {
+		dst.reserve(page.header().len() + page.data().len());
+		dst.put_slice(page.header());
+		dst.put_slice(page.data());
+		Ok(())
+	}
+}
+
+impl Encoder<Page> for PageCodec {
+	type Error = Error;
+
+	fn encode(&mut self, page: Page, dst: &mut BytesMut) -> Result<(), Error> {
+		self.encode(&page, dst)
+	}
+}
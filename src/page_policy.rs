@@ -0,0 +1,140 @@
+use std::{
+	num::NonZeroUsize,
+	time::{ Duration, Instant }
+};
+
+/// The packets added to a [Stream](crate::Stream) that aren't on
+/// a page yet, as seen by a [PagePolicy].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PendingPackets {
+	/// The number of packets, counting one that is partly on
+	/// a page already.
+	pub packets: usize,
+	/// The number of bytes of packet data.
+	pub bytes: usize,
+	/// The granule position the last page ended at, if it's known.
+	pub page_granule: Option<u64>,
+	/// The granule position of the last packet, if it's known.
+	pub granule: Option<u64>,
+	/// The number of granule units between the last two packets,
+	/// if both granule positions are known.
+	pub last_duration: Option<u64>,
+	/// When the first of the packets was added.
+	pub since: Option<Instant>
+}
+
+impl PendingPackets {
+	/// Return the number of granule units the packets span, if
+	/// it's known.
+	pub fn granule_span(&self) -> Option<u64> {
+		Some(self.granule?.saturating_sub(self.page_granule?))
+	}
+
+	/// Return how long the first of the packets has waited.
+	pub fn elapsed(&self) -> Duration {
+		self.since.map(|since| since.elapsed()).unwrap_or_default()
+	}
+
+	/// Update for a packet added to the stream.
+	pub(crate) fn packet_in(&mut self, bytes: usize, absgp: u64) {
+		let granule = if absgp == u64::MAX { None } else { Some(absgp) };
+		self.last_duration = match (self.granule, granule) {
+			(Some(last), Some(granule)) => Some(granule.saturating_sub(last)),
+			_ => None
+		};
+		self.granule = granule;
+		self.packets += 1;
+		self.bytes += bytes;
+		self.since.get_or_insert_with(Instant::now);
+	}
+
+	/// Update for a page taken out of the stream, with `remaining`
+	/// lacing values left in it.
+	pub(crate) fn page_out(&mut self, packets: usize, bytes: usize, absgp: u64, remaining: usize) {
+		if absgp != u64::MAX {
+			self.page_granule = Some(absgp)
+		}
+		if remaining == 0 {
+			self.packets = 0;
+			self.bytes = 0;
+			self.since = None
+		} else {
+			self.packets = self.packets.saturating_sub(packets);
+			self.bytes = self.bytes.saturating_sub(bytes)
+		}
+	}
+}
+
+/// Decides when a [Stream](crate::Stream) puts its packets on pages.
+/// 
+/// A policy is set with [Stream::set_page_policy](crate::Stream::set_page_policy)
+/// and is asked after every packet added to the stream. When it
+/// returns `true`, [page_out](crate::Stream::page_out) flushes
+/// every packet added so far, as [page_flush](crate::Stream::page_flush)
+/// would. Otherwise pages come out when they are filled to
+/// [page_size](PagePolicy::page_size).
+/// 
+/// A page ends at the granule position of the last packet that
+/// finishes on it, so a policy should only flush after a packet
+/// with a known granule position.
+pub trait PagePolicy {
+	/// Check whether the pending packets should be put on pages now.
+	fn flush(&mut self, pending: &PendingPackets) -> bool;
+
+	/// Return the size pages are filled to before they come out.
+	/// 
+	/// The default lets ogg decide, which is about 4 kB.
+	fn page_size(&self) -> Option<NonZeroUsize> {
+		None
+	}
+}
+
+/// Puts packets on pages as soon as possible, for live streaming.
+/// 
+/// Pages are flushed after every packet with a known granule
+/// position once the first pending packet has waited `max_delay`.
+/// The default of no delay puts every such packet on its own page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LowLatency {
+	pub max_delay: Duration
+}
+
+impl PagePolicy for LowLatency {
+	fn flush(&mut self, pending: &PendingPackets) -> bool {
+		pending.granule.is_some() && pending.elapsed() >= self.max_delay
+	}
+}
+
+/// Fills pages as far as they go, for storage.
+/// 
+/// Pages are never flushed early, and come out once they hold
+/// about 64 kB or 255 segments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MinimalOverhead;
+
+impl PagePolicy for MinimalOverhead {
+	fn flush(&mut self, _: &PendingPackets) -> bool {
+		false
+	}
+
+	fn page_size(&self) -> Option<NonZeroUsize> {
+		NonZeroUsize::new(255 * 255)
+	}
+}
+
+/// Keeps pages of an Opus stream at one second or less, as
+/// recommended by RFC 7845.
+/// 
+/// Pages are flushed before the next packet, if it's as long as
+/// the last one, would take them past one second at 48 kHz.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpusRecommended;
+
+impl PagePolicy for OpusRecommended {
+	fn flush(&mut self, pending: &PendingPackets) -> bool {
+		match (pending.granule_span(), pending.last_duration) {
+			(Some(span), Some(duration)) => span + duration > 48000,
+			_ => false
+		}
+	}
+}
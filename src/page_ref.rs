@@ -0,0 +1,256 @@
+#[cfg(feature = "mmap")]
+use std::{ fs::File, io };
+
+use crate::Page;
+use crate::page::*;
+
+/// The CRC-32 lookup table used by Ogg, with the polynomial
+/// `0x04c11db7`, no reflection and no final xor.
+const CRC_TABLE: [u32; 256] = {
+	let mut table = [0; 256];
+	let mut index = 0;
+	while index < 256 {
+		let mut crc = (index as u32) << 24;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+			bit += 1
+		}
+		table[index] = crc;
+		index += 1
+	}
+	table
+};
+
+fn crc_update(crc: u32, bytes: &[u8]) -> u32 {
+	bytes.iter().fold(crc, |crc, byte| (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize])
+}
+
+/// A page borrowed from bytes in memory, such as a memory mapped
+/// file, without copying it.
+/// 
+/// Unlike a [Page] read through a [SyncState](crate::SyncState),
+/// a `PageRef` points straight into the bytes it was parsed from.
+/// Use [PageRef::to_page] to get an owned copy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageRef<'a> {
+	header: &'a [u8],
+	body: &'a [u8]
+}
+
+impl<'a> PageRef<'a> {
+	/// Parse the page at the start of `bytes`.
+	/// 
+	/// The header is validated, but the checksum isn't, see
+	/// [verify_crc_checksum](PageRef::verify_crc_checksum).
+	/// Returns [InvalidPageHeader::BadLength] if `bytes` ends
+	/// before the page does.
+	pub fn parse(bytes: &'a [u8]) -> Result<Self, InvalidPageHeader> {
+		validate_header(bytes)?;
+		let header_size = HEADER_SIZE_MIN + bytes[HEADER_SEGMENTS] as usize;
+		let Some(lacing_values) = bytes.get(HEADER_SIZE_MIN..header_size) else {
+			return Err(InvalidPageHeader::BadLength)
+		};
+		let body_size = lacing_values.iter().map(|value| *value as usize).sum::<usize>();
+		match bytes.get(header_size..header_size + body_size) {
+			Some(body) => Ok(Self { header: &bytes[..header_size], body }),
+			None => Err(InvalidPageHeader::BadLength)
+		}
+	}
+
+	/// Return the header of this `PageRef`.
+	pub fn header(&self) -> &'a [u8] {
+		self.header
+	}
+
+	/// Return the packet data of this `PageRef`.
+	pub fn data(&self) -> &'a [u8] {
+		self.body
+	}
+
+	/// Return the size of this `PageRef` in bytes, including
+	/// the header.
+	pub fn size(&self) -> usize {
+		self.header.len() + self.body.len()
+	}
+
+	/// Returns the page header type, see [Page::header_type].
+	pub fn header_type(&self) -> u8 {
+		self.header[HEADER_TYPE]
+	}
+
+	/// Check whether this page contains packet data that continues
+	/// from the last page.
+	pub fn continues_packet(&self) -> bool {
+		self.header_type() & HEADER_TYPE_CONTINUED != 0
+	}
+
+	/// Check whether this page begins a logical stream.
+	pub fn begins_logical_stream(&self) -> bool {
+		self.header_type() & HEADER_TYPE_BEGINNING != 0
+	}
+
+	/// Check whether this page ends a logical stream.
+	pub fn ends_logical_stream(&self) -> bool {
+		self.header_type() & HEADER_TYPE_END != 0
+	}
+
+	/// Return the lacing values of this page, see [Page::lacing_values].
+	pub fn lacing_values(&self) -> &'a [u8] {
+		&self.header[HEADER_SIZE_MIN..]
+	}
+
+	/// Return the number of packets that completed on this page,
+	/// see [Page::finished_packets].
+	pub fn finished_packets(&self) -> u8 {
+		self.lacing_values().iter().filter(|value| **value < 255).count() as u8
+	}
+
+	/// Return the absolute granule position of the packet data
+	/// at the end of this page.
+	pub fn absgp(&self) -> u64 {
+		u64::from_le_bytes(self.header[HEADER_GRANULE_POSITION..HEADER_GRANULE_POSITION + 8].try_into().unwrap())
+	}
+
+	/// Return the serial number of the logical stream that this
+	/// page is associated with.
+	pub fn stream_serial(&self) -> i32 {
+		i32::from_le_bytes(self.header[HEADER_PAGE_SERIAL_NUMBER..HEADER_PAGE_SERIAL_NUMBER + 4].try_into().unwrap())
+	}
+
+	/// Return the sequential number for this page.
+	pub fn index(&self) -> u32 {
+		u32::from_le_bytes(self.header[HEADER_SEQUENCE_NUMBER..HEADER_SEQUENCE_NUMBER + 4].try_into().unwrap())
+	}
+
+	/// Return the CRC checksum in the header of this page.
+	pub fn crc_checksum(&self) -> u32 {
+		u32::from_le_bytes(self.header[HEADER_CHECKSUM..HEADER_CHECKSUM + 4].try_into().unwrap())
+	}
+
+	/// Check whether the CRC checksum in the header of this page
+	/// matches its contents.
+	/// 
+	/// The checksum is computed in place, taking the checksum
+	/// field as zero.
+	pub fn verify_crc_checksum(&self) -> bool {
+		let crc = crc_update(0, &self.header[..HEADER_CHECKSUM]);
+		let crc = crc_update(crc, &[0; 4]);
+		let crc = crc_update(crc, &self.header[HEADER_CHECKSUM + 4..]);
+		crc_update(crc, self.body) == self.crc_checksum()
+	}
+
+	/// Copy this page into an owned [Page].
+	pub fn to_page(&self) -> Page {
+		Page::from_parts(self.header.to_vec(), self.body.to_vec()).expect("header was validated")
+	}
+}
+
+impl From<PageRef<'_>> for Page {
+	fn from(page: PageRef<'_>) -> Self {
+		page.to_page()
+	}
+}
+
+/// An iterator over every page in bytes in memory, along with its
+/// offset, without copying them.
+/// 
+/// Every page is found by its capture pattern and only yielded if
+/// its header is valid and its checksum matches, so bytes that
+/// aren't part of a page are skipped, as [SyncState](crate::SyncState)
+/// would. Returned by [scan_pages].
+#[derive(Clone, Debug)]
+pub struct PageScanner<'a> {
+	bytes: &'a [u8],
+	position: usize,
+	skipped: u64
+}
+
+impl<'a> PageScanner<'a> {
+	/// Return the offset the next page is looked for at.
+	pub fn offset(&self) -> u64 {
+		self.position as u64
+	}
+
+	/// Return the number of bytes skipped so far because they
+	/// weren't part of a valid page.
+	pub fn skipped(&self) -> u64 {
+		self.skipped
+	}
+}
+
+impl<'a> Iterator for PageScanner<'a> {
+	type Item = (u64, PageRef<'a>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.position < self.bytes.len() {
+			let rest = &self.bytes[self.position..];
+			let Some(start) = rest.windows(4).position(|magic| magic == b"OggS") else {
+				self.skipped += rest.len() as u64;
+				self.position = self.bytes.len();
+				return None
+			};
+			let offset = self.position + start;
+			match PageRef::parse(&self.bytes[offset..]) {
+				Ok(page) if page.verify_crc_checksum() => {
+					self.skipped += start as u64;
+					self.position = offset + page.size();
+					return Some((offset as u64, page))
+				},
+				// Look for the next capture pattern past this one
+				_ => {
+					self.skipped += start as u64 + 1;
+					self.position = offset + 1
+				}
+			}
+		}
+		None
+	}
+}
+
+/// Return an iterator over every valid page in `bytes`, see
+/// [PageScanner].
+pub fn scan_pages(bytes: &[u8]) -> PageScanner<'_> {
+	PageScanner { bytes, position: 0, skipped: 0 }
+}
+
+/// A file mapped into memory, to scan its pages at disk speed
+/// without reading them into buffers.
+#[cfg(feature = "mmap")]
+pub struct MappedFile {
+	map: memmap2::Mmap
+}
+
+#[cfg(feature = "mmap")]
+impl MappedFile {
+	/// Map `file` into memory.
+	/// 
+	/// # Safety
+	/// 
+	/// The file must not be changed or truncated, by this process
+	/// or another, while it is mapped. See [memmap2::Mmap::map].
+	pub unsafe fn map(file: &File) -> io::Result<Self> {
+		Ok(Self { map: memmap2::Mmap::map(file)? })
+	}
+
+	/// Return the bytes of the file.
+	pub fn bytes(&self) -> &[u8] {
+		&self.map
+	}
+
+	/// Return an iterator over every valid page in the file.
+	pub fn pages(&self) -> PageScanner<'_> {
+		scan_pages(&self.map)
+	}
+}
+
+#[cfg(feature = "mmap")]
+impl crate::ReadAt for MappedFile {
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		self.bytes().read_at(offset, buf)
+	}
+
+	fn size(&self) -> io::Result<u64> {
+		Ok(self.map.len() as u64)
+	}
+}
@@ -0,0 +1,255 @@
+use std::{
+	collections::{ HashMap, VecDeque },
+	fs::File,
+	io::{ self, Read, Seek, SeekFrom },
+	sync::Mutex
+};
+
+/// A source of bytes that can be read at any offset, such as a
+/// file, a buffer in memory or a server taking range requests.
+/// 
+/// Unlike [Seek], reading doesn't move a shared position, so a
+/// source can be read from several places at once. Use
+/// [ReadAtCursor] to read one with any [Read] + [Seek] API of
+/// this crate, such as [duration](crate::duration) or
+/// [PageIndex::load](crate::PageIndex::load).
+pub trait ReadAt {
+	/// Read bytes starting at `offset` into `buf`, returning how
+	/// many were read. This is only less than the length of `buf`
+	/// at the end of the source, or 0 past it.
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+	/// Return the size of the source in bytes.
+	fn size(&self) -> io::Result<u64>;
+
+	/// Read exactly enough bytes at `offset` to fill `buf`.
+	fn read_exact_at(&self, mut offset: u64, mut buf: &mut [u8]) -> io::Result<()> {
+		while !buf.is_empty() {
+			match self.read_at(offset, buf) {
+				Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+				Ok(read) => {
+					buf = &mut buf[read..];
+					offset += read as u64
+				},
+				Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+				Err(error) => return Err(error)
+			}
+		}
+		Ok(())
+	}
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		(**self).read_at(offset, buf)
+	}
+
+	fn size(&self) -> io::Result<u64> {
+		(**self).size()
+	}
+}
+
+impl ReadAt for [u8] {
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		let start = usize::try_from(offset).unwrap_or(usize::MAX).min(self.len());
+		let read = buf.len().min(self.len() - start);
+		buf[..read].copy_from_slice(&self[start..start + read]);
+		Ok(read)
+	}
+
+	fn size(&self) -> io::Result<u64> {
+		Ok(self.len() as u64)
+	}
+}
+
+impl ReadAt for Vec<u8> {
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		self.as_slice().read_at(offset, buf)
+	}
+
+	fn size(&self) -> io::Result<u64> {
+		Ok(self.len() as u64)
+	}
+}
+
+#[cfg(any(unix, windows))]
+impl ReadAt for File {
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		#[cfg(unix)]
+		{ std::os::unix::fs::FileExt::read_at(self, buf, offset) }
+		#[cfg(windows)]
+		{ std::os::windows::fs::FileExt::seek_read(self, buf, offset) }
+	}
+
+	fn size(&self) -> io::Result<u64> {
+		Ok(self.metadata()?.len())
+	}
+}
+
+/// The blocks kept by a [CachedReader], oldest first.
+struct BlockCache {
+	blocks: HashMap<u64, Vec<u8>>,
+	order: VecDeque<u64>,
+	size: Option<u64>
+}
+
+/// Reads a [ReadAt] in blocks of a fixed size and keeps the most
+/// recently used ones, so a slow source is read as little and in
+/// as few requests as possible.
+pub struct CachedReader<R> {
+	inner: R,
+	block_size: usize,
+	capacity: usize,
+	cache: Mutex<BlockCache>
+}
+
+impl<R: ReadAt> CachedReader<R> {
+	/// Return a new `CachedReader` keeping up to `capacity` blocks
+	/// of `block_size` bytes.
+	/// 
+	/// # Panics
+	/// 
+	/// Panics if `block_size` or `capacity` is 0.
+	pub fn new(inner: R, block_size: usize, capacity: usize) -> Self {
+		assert!(block_size > 0 && capacity > 0, "the block size and capacity of a CachedReader can't be 0");
+		Self {
+			inner,
+			block_size,
+			capacity,
+			cache: Mutex::new(BlockCache { blocks: HashMap::new(), order: VecDeque::new(), size: None })
+		}
+	}
+
+	/// Return the underlying source.
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+
+	/// Copy from the kept block at `block` into `buf`, starting
+	/// `skip` bytes into it.
+	fn copy_block(&self, cache: &mut BlockCache, block: u64, skip: usize, buf: &mut [u8]) -> usize {
+		cache.order.retain(|kept| *kept != block);
+		cache.order.push_back(block);
+
+		let data = &cache.blocks[&block];
+		let read = buf.len().min(data.len().saturating_sub(skip));
+		buf[..read].copy_from_slice(&data[skip..skip + read]);
+		read
+	}
+
+	/// Read the blocks from `start` up to `end` with one request,
+	/// keep them, and copy from them into `buf`, starting `skip`
+	/// bytes into the first one.
+	fn read_blocks(&self, cache: &mut BlockCache, start: u64, end: u64, skip: usize, buf: &mut [u8]) -> io::Result<usize> {
+		let offset = start * self.block_size as u64;
+		let mut data = vec![0; (end - start) as usize * self.block_size];
+		let mut filled = 0;
+		while filled < data.len() {
+			match self.inner.read_at(offset + filled as u64, &mut data[filled..]) {
+				Ok(0) => break,
+				Ok(read) => filled += read,
+				Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+				Err(error) => return Err(error)
+			}
+		}
+		data.truncate(filled);
+
+		let read = buf.len().min(data.len().saturating_sub(skip));
+		buf[..read].copy_from_slice(&data[skip..skip + read]);
+
+		for (index, block) in (start..end).enumerate() {
+			let from = (index * self.block_size).min(data.len());
+			let to = (from + self.block_size).min(data.len());
+			if cache.order.len() >= self.capacity {
+				if let Some(oldest) = cache.order.pop_front() {
+					cache.blocks.remove(&oldest);
+				}
+			}
+			cache.blocks.insert(block, data[from..to].to_vec());
+			cache.order.push_back(block)
+		}
+		Ok(read)
+	}
+}
+
+impl<R: ReadAt> ReadAt for CachedReader<R> {
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() { return Ok(0) }
+		let mut cache = self.cache.lock().unwrap_or_else(|error| error.into_inner());
+		let block_size = self.block_size as u64;
+		let last = offset.saturating_add(buf.len() as u64 - 1) / block_size;
+		let mut read = 0;
+		while read < buf.len() {
+			let position = offset + read as u64;
+			let block = position / block_size;
+			let skip = (position % block_size) as usize;
+			let length = if cache.blocks.contains_key(&block) {
+				self.copy_block(&mut cache, block, skip, &mut buf[read..])
+			} else {
+				// Every block up to the next kept one is read at once
+				let end = (block + 1..=last).find(|block| cache.blocks.contains_key(block)).unwrap_or(last + 1);
+				self.read_blocks(&mut cache, block, end, skip, &mut buf[read..])?
+			};
+			match length {
+				0 => break,
+				length => read += length
+			}
+		}
+		Ok(read)
+	}
+
+	fn size(&self) -> io::Result<u64> {
+		let mut cache = self.cache.lock().unwrap_or_else(|error| error.into_inner());
+		if let Some(size) = cache.size { return Ok(size) }
+		let size = self.inner.size()?;
+		cache.size = Some(size);
+		Ok(size)
+	}
+}
+
+/// Reads a [ReadAt] through [Read] and [Seek], keeping its own
+/// position.
+pub struct ReadAtCursor<R> {
+	source: R,
+	position: u64
+}
+
+impl<R: ReadAt> ReadAtCursor<R> {
+	/// Return a new `ReadAtCursor` at the start of `source`.
+	pub fn new(source: R) -> Self {
+		Self { source, position: 0 }
+	}
+
+	/// Return the underlying source.
+	pub fn into_inner(self) -> R {
+		self.source
+	}
+}
+
+impl<R: ReadAt> Read for ReadAtCursor<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = self.source.read_at(self.position, buf)?;
+		self.position += read as u64;
+		Ok(read)
+	}
+}
+
+impl<R: ReadAt> Seek for ReadAtCursor<R> {
+	fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+		let (base, offset) = match position {
+			SeekFrom::Start(offset) => {
+				self.position = offset;
+				return Ok(offset)
+			},
+			SeekFrom::End(offset) => (self.source.size()?, offset),
+			SeekFrom::Current(offset) => (self.position, offset)
+		};
+		match base.checked_add_signed(offset) {
+			Some(position) => {
+				self.position = position;
+				Ok(position)
+			},
+			None => Err(io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative or overflowing position"))
+		}
+	}
+}
@@ -0,0 +1,397 @@
+use std::{
+	collections::{ hash_map::Entry, HashMap, VecDeque },
+	io::Read
+};
+
+use crate::{ Error, Packet, PacketOutError, Page, PageSeek, PageWriteError, Stream, SyncState };
+use crate::page::{ HEADER_PAGE_SERIAL_NUMBER, HEADER_SEGMENTS, HEADER_SEQUENCE_NUMBER };
+
+/// The number of bytes read from the reader at a time.
+const READ_SIZE: usize = 4096;
+
+/// Damage found while reading a physical stream.
+/// 
+/// All offsets are in bytes from the start of the reader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Damage {
+	/// Bytes that don't belong to any page were skipped.
+	SkippedBytes { offset: u64, length: u64 },
+	/// A page was found but its checksum is wrong, so it was
+	/// skipped. The skipped bytes are also reported as
+	/// [SkippedBytes](Damage::SkippedBytes), unless the page is
+	/// kept with [PageReader::set_keep_bad_checksums].
+	BadChecksum { offset: u64, length: u64, serial: i32, index: u32 },
+	/// The reader ended in the middle of a page.
+	Truncated { offset: u64, length: u64 },
+	/// A page of a logical stream didn't have the next
+	/// sequence number, so pages are missing or out of order.
+	SequenceGap { offset: u64, serial: i32, expected: u32, found: u32 },
+	/// Packets of a logical stream were lost because of a hole
+	/// in the data, noticed when adding the page at `offset`.
+	PacketsLost { offset: u64, serial: i32 }
+}
+
+impl Damage {
+	/// Return the offset this damage was found at.
+	pub fn offset(&self) -> u64 {
+		match self {
+			Self::SkippedBytes { offset, .. }
+			| Self::BadChecksum { offset, .. }
+			| Self::Truncated { offset, .. }
+			| Self::SequenceGap { offset, .. }
+			| Self::PacketsLost { offset, .. } => *offset
+		}
+	}
+}
+
+impl std::fmt::Display for Damage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::SkippedBytes { offset, length } => write!(f, "{}: skipped {} bytes", offset, length),
+			Self::BadChecksum { offset, length, serial, index } => write!(f, "{}: page {} of stream {} ({} bytes) has a bad checksum", offset, index, serial, length),
+			Self::Truncated { offset, length } => write!(f, "{}: data ends {} bytes into a page", offset, length),
+			Self::SequenceGap { offset, serial, expected, found } => write!(f, "{}: expected page {} of stream {} but found page {}", offset, expected, serial, found),
+			Self::PacketsLost { offset, serial } => write!(f, "{}: packets of stream {} were lost", offset, serial)
+		}
+    }
+}
+
+/// Reads [Pages](Page) from a [Read], along with the offset
+/// of each page.
+/// 
+/// This reader tolerates damage. Bytes that don't belong to a
+/// page are skipped, and anything unusual is recorded as
+/// [Damage] instead of being returned as an error. Use
+/// [take_damage](PageReader::take_damage) to find out what was
+/// found so far.
+pub struct PageReader<R> {
+	reader: R,
+	sync_state: SyncState,
+	buffer: Box<[u8]>,
+	/// The offset of the first byte the sync state hasn't
+	/// returned or skipped yet.
+	offset: u64,
+	/// The bytes skipped since the last page, as `(offset, length)`.
+	skipped: Option<(u64, u64)>,
+	/// The next sequence number expected for every logical stream.
+	sequence: HashMap<i32, u32>,
+	pages_read: u64,
+	damage: Vec<Damage>,
+	keep_bad_checksums: bool,
+	end_of_file: bool
+}
+
+impl<R: Read> PageReader<R> {
+	/// Return a new `PageReader` reading from `reader`.
+	pub fn new(reader: R) -> Result<Self, Error> {
+		Ok(Self {
+			reader,
+			sync_state: SyncState::new()?,
+			buffer: vec![0; READ_SIZE].into_boxed_slice(),
+			offset: 0,
+			skipped: None,
+			sequence: HashMap::new(),
+			pages_read: 0,
+			damage: vec![],
+			keep_bad_checksums: false,
+			end_of_file: false
+		})
+	}
+
+	/// Return the damage found since this was last called.
+	pub fn take_damage(&mut self) -> Vec<Damage> {
+		std::mem::take(&mut self.damage)
+	}
+
+	/// Set whether pages with a bad checksum are returned
+	/// instead of skipped.
+	/// 
+	/// They are still reported as [Damage::BadChecksum].
+	/// This is off by default.
+	pub fn set_keep_bad_checksums(&mut self, keep_bad_checksums: bool) {
+		self.keep_bad_checksums = keep_bad_checksums
+	}
+
+	/// Return the number of bytes read and processed so far.
+	pub fn offset(&self) -> u64 {
+		self.offset
+	}
+
+	/// Return the number of pages read so far.
+	pub fn pages_read(&self) -> u64 {
+		self.pages_read
+	}
+
+	/// Return the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+
+	/// Record skipped bytes, merging them with bytes skipped
+	/// right before.
+	/// 
+	/// Returns the skipped page if it only has a bad checksum
+	/// and those are being kept.
+	fn skip(&mut self, length: usize) -> Result<Option<Page>, Error> {
+		// The skipped bytes are still in the buffer of the sync state
+		let pending = self.sync_state.buffered(length);
+		if pending.starts_with(b"OggS") && pending.len() > HEADER_SEGMENTS {
+			// ogg only skips bytes starting with a capture pattern
+			// if the page checksum is wrong
+			let segments = pending[HEADER_SEGMENTS] as usize;
+			if let Some(lacing) = pending.get(HEADER_SEGMENTS + 1..HEADER_SEGMENTS + 1 + segments) {
+				let header_length = HEADER_SEGMENTS + 1 + segments;
+				let page_length = header_length + lacing.iter().map(|value| *value as usize).sum::<usize>();
+				if page_length <= pending.len() {
+					self.damage.push(Damage::BadChecksum {
+						offset: self.offset,
+						length: page_length as u64,
+						serial: i32::from_le_bytes(pending[HEADER_PAGE_SERIAL_NUMBER..HEADER_PAGE_SERIAL_NUMBER + 4].try_into().unwrap()),
+						index: u32::from_le_bytes(pending[HEADER_SEQUENCE_NUMBER..HEADER_SEQUENCE_NUMBER + 4].try_into().unwrap())
+					});
+
+					if self.keep_bad_checksums {
+						if let Ok(page) = Page::from_parts(pending[..header_length].to_vec(), pending[header_length..page_length].to_vec()) {
+							// ogg only skipped part of the page, so start
+							// over right after it
+							let rest = pending[page_length..].to_vec();
+							self.sync_state.reset();
+							self.sync_state.write(&rest);
+							return Ok(Some(page))
+						}
+					}
+				}
+			}
+		}
+
+		self.skipped = match self.skipped {
+			None => Some((self.offset, length as u64)),
+			Some((offset, skipped)) => Some((offset, skipped + length as u64))
+		};
+		self.consume(length);
+		Ok(None)
+	}
+
+	/// Report the bytes skipped since the last page, if any.
+	fn end_skip(&mut self) {
+		if let Some((offset, length)) = self.skipped.take() {
+			self.damage.push(Damage::SkippedBytes { offset, length })
+		}
+	}
+
+	fn consume(&mut self, length: usize) {
+		self.offset += length as u64;
+	}
+
+	/// Check the sequence number of a page.
+	/// 
+	/// The count starts over at every beginning of stream page,
+	/// since a later link of a chained file can reuse a serial
+	/// number.
+	fn check_sequence(&mut self, offset: u64, page: &Page) {
+		let serial = page.stream_serial();
+		let found = page.index();
+		if page.begins_logical_stream() {
+			self.sequence.remove(&serial);
+		}
+		match self.sequence.entry(serial) {
+			Entry::Vacant(entry) => { entry.insert(found.wrapping_add(1)); },
+			Entry::Occupied(mut entry) => {
+				let expected = *entry.get();
+				if found != expected {
+					self.damage.push(Damage::SequenceGap { offset, serial, expected, found })
+				}
+				entry.insert(found.wrapping_add(1));
+			}
+		}
+		if page.ends_logical_stream() {
+			self.sequence.remove(&serial);
+		}
+	}
+
+	/// Account for a page found at the current offset.
+	fn page_found(&mut self, page: Page) -> (u64, Page) {
+		self.end_skip();
+		let offset = self.offset;
+		self.consume(page.size());
+		self.check_sequence(offset, &page);
+		self.pages_read += 1;
+		(offset, page)
+	}
+
+	/// Read the next page and the offset it starts at.
+	pub fn next_page(&mut self) -> Result<Option<(u64, Page)>, Error> {
+		loop {
+			match self.sync_state.page_seek() {
+				Ok(PageSeek::Page(page)) => return Ok(Some(self.page_found(page))),
+				Ok(PageSeek::Skipped(length)) => match self.skip(length)? {
+					Some(page) => return Ok(Some(self.page_found(page))),
+					None => continue
+				},
+				Err(PageWriteError::NeedMoreData) => {},
+				Err(error) => return Err(error.into())
+			}
+
+			if self.end_of_file {
+				self.end_skip();
+				let pending = self.sync_state.buffered(0);
+				if !pending.is_empty() {
+					let length = pending.len();
+					if pending.starts_with(b"OggS") {
+						self.damage.push(Damage::Truncated { offset: self.offset, length: length as u64 })
+					} else {
+						self.damage.push(Damage::SkippedBytes { offset: self.offset, length: length as u64 })
+					}
+					self.consume(length);
+				}
+				return Ok(None)
+			}
+
+			let read = match self.reader.read(&mut self.buffer) {
+				Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+				Err(error) => return Err(error.into()),
+				Ok(read) => read
+			};
+			if read == 0 {
+				self.end_of_file = true
+			} else {
+				self.sync_state.write(&self.buffer[..read]);
+			}
+		}
+	}
+}
+
+impl<R: Read> Iterator for PageReader<R> {
+	type Item = Result<(u64, Page), Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_page().transpose()
+	}
+}
+
+/// Reads [Packets](Packet) from a [Read].
+/// 
+/// Every logical stream in the input gets its own [Stream], and
+/// each packet is returned along with the serial number of the
+/// logical stream it belongs to.
+/// 
+/// Like [PageReader], this tolerates damage. Packets lost to
+/// holes in the data are recorded as [Damage::PacketsLost].
+pub struct PacketReader<R> {
+	pages: PageReader<R>,
+	streams: HashMap<i32, Stream>,
+	packets: VecDeque<(i32, Packet)>,
+	damage: Vec<Damage>
+}
+
+impl<R: Read> PacketReader<R> {
+	/// Return a new `PacketReader` reading from `reader`.
+	pub fn new(reader: R) -> Result<Self, Error> {
+		Ok(Self {
+			pages: PageReader::new(reader)?,
+			streams: HashMap::new(),
+			packets: VecDeque::new(),
+			damage: vec![]
+		})
+	}
+
+	/// Return the damage found since this was last called.
+	pub fn take_damage(&mut self) -> Vec<Damage> {
+		let mut damage = self.pages.take_damage();
+		damage.append(&mut self.damage);
+		damage.sort_by_key(Damage::offset);
+		damage
+	}
+
+	/// Return the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.pages.into_inner()
+	}
+
+	/// Add a page to its logical stream and queue all of the
+	/// packets that could be completed.
+	fn page_in(&mut self, offset: u64, mut page: Page) -> Result<(), Error> {
+		let serial = page.stream_serial();
+		let stream = match self.streams.entry(serial) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) => entry.insert(Stream::new(serial)?)
+		};
+		stream.page_in(&mut page)?;
+
+		for packet in stream.packets() {
+			match packet {
+				Ok(packet) => self.packets.push_back((serial, packet)),
+				Err(PacketOutError::OutOfSync) => self.damage.push(Damage::PacketsLost { offset, serial }),
+				Err(error) => return Err(error.into())
+			}
+		}
+
+		if page.ends_logical_stream() {
+			self.streams.remove(&serial);
+		}
+
+		Ok(())
+	}
+
+	/// Read the next packet and the serial number of its
+	/// logical stream.
+	pub fn next_packet(&mut self) -> Result<Option<(i32, Packet)>, Error> {
+		loop {
+			if let Some(packet) = self.packets.pop_front() {
+				return Ok(Some(packet))
+			}
+
+			match self.pages.next_page()? {
+				None => return Ok(None),
+				Some((offset, page)) => self.page_in(offset, page)?
+			}
+		}
+	}
+}
+
+impl<R: Read> Iterator for PacketReader<R> {
+	type Item = Result<(i32, Packet), Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_packet().transpose()
+	}
+}
+
+/// A summary of the damage in a physical stream.
+/// 
+/// See [damage_report].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DamageReport {
+	/// The total number of bytes read.
+	pub bytes: u64,
+	/// The number of pages that could be read.
+	pub pages: u64,
+	/// The number of packets that could be read.
+	pub packets: u64,
+	/// All of the damage found, ordered by offset.
+	pub damage: Vec<Damage>
+}
+
+impl DamageReport {
+	/// Check whether no damage was found.
+	pub fn is_intact(&self) -> bool {
+		self.damage.is_empty()
+	}
+}
+
+/// Read all of `reader` and report any damage found in it.
+pub fn damage_report<R: Read>(reader: R) -> Result<DamageReport, Error> {
+	let mut packets = PacketReader::new(reader)?;
+	let mut report = DamageReport::default();
+
+	loop {
+		if packets.next_packet()?.is_none() { break }
+		report.packets += 1;
+	}
+
+	report.pages = packets.pages.pages_read();
+	report.bytes = packets.pages.offset();
+	report.damage = packets.take_damage();
+
+	Ok(report)
+}
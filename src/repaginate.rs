@@ -0,0 +1,237 @@
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	io::{ Read, Write },
+	num::NonZeroUsize
+};
+
+use crate::{ Codec, Error, Packet, PageOutError, PageReader, Stream, Timestamper };
+
+/// The largest a page can be: a full header and 255 full segments.
+pub(crate) const MAX_PAGE_SIZE: usize = 27 + 255 + 255 * 255;
+
+/// How [repaginate] puts packets on pages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PagePacing {
+	/// Pages are kept around this many bytes of packet data.
+	MaxPageBytes (NonZeroUsize),
+	/// Pages span at most about this many granule units, such as
+	/// samples for audio.
+	MaxPageGranules (u64),
+	/// Every packet goes on a page of its own.
+	OnePacketPerPage
+}
+
+/// What [repaginate] did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RepaginateSummary {
+	pub pages_read: u64,
+	pub pages_written: u64,
+	pub packets: u64
+}
+
+/// The state of one logical stream being repaginated.
+struct Repaginator {
+	input: Timestamper,
+	output: Stream,
+	header_packets: usize,
+	packets: usize,
+	/// Data packets after the last one with a known granule
+	/// position, held back until a page can end after them.
+	held: Vec<Packet>,
+	/// The number of packets, bytes and lacing values since the
+	/// last page.
+	pending_packets: usize,
+	pending_bytes: usize,
+	pending_segments: usize,
+	/// The granule position of the last page.
+	page_start: u64,
+	/// The granule position of the first data packet.
+	first_granule: Option<u64>,
+	/// The granule position of the last packet, if it's known.
+	last_granule: Option<u64>,
+	/// The size and granule units of the last data packet, to
+	/// guess whether the next one fits on the page.
+	last_bytes: usize,
+	last_duration: u64
+}
+
+impl Repaginator {
+	/// Write every packet added so far.
+	fn flush<W: Write>(&mut self, writer: &mut W, summary: &mut RepaginateSummary) -> Result<(), Error> {
+		// Pages are only split here, after a packet with a known
+		// granule position, so ogg never has to
+		let size = NonZeroUsize::new(MAX_PAGE_SIZE).unwrap();
+		loop {
+			match self.output.page_flush_with_max_size(size) {
+				Ok(page) => {
+					// ogg gives the first page the granule position 0,
+					// as if it was a header
+					let first = self.first_granule.filter(|&granule| granule != u64::MAX);
+					let page = match first {
+						Some(granule) if page.begins_logical_stream() && self.header_packets == 0 => {
+							let mut page = page.clone();
+							page.set_absgp(granule);
+							page.set_crc_checksum();
+							Cow::Owned(page)
+						},
+						_ => Cow::Borrowed(page)
+					};
+					writer.write_all(page.header())?;
+					writer.write_all(page.data())?;
+					summary.pages_written += 1
+				},
+				Err(PageOutError::NeedMoreData) => break,
+				Err(error) => return Err(error.into())
+			}
+		}
+
+		self.pending_packets = 0;
+		self.pending_bytes = 0;
+		self.pending_segments = 0;
+		if let Some(granule) = self.last_granule {
+			self.page_start = granule
+		}
+		Ok(())
+	}
+
+	fn packet_in<W: Write>(&mut self, writer: &mut W, mut packet: Packet, pacing: PagePacing, summary: &mut RepaginateSummary) -> Result<(), Error> {
+		summary.packets += 1;
+
+		if self.packets < self.header_packets {
+			// The first header is alone on the first page, and
+			// the rest end on a page of their own
+			packet.set_absgp(0);
+			self.output.packet_in(&packet)?;
+			self.packets += 1;
+			if self.packets == 1 || self.packets == self.header_packets {
+				self.last_granule = Some(0);
+				self.flush(writer, summary)?
+			}
+			return Ok(())
+		}
+		self.packets += 1;
+
+		// A page can only end after a packet with a known
+		// granule position, so the packets before one are held
+		let ends_stream = packet.ends_logical_stream();
+		let granule = Some(packet.absgp()).filter(|&absgp| absgp != u64::MAX);
+		self.held.push(packet);
+		if granule.is_none() && !ends_stream { return Ok(()) }
+
+		let mut held = std::mem::take(&mut self.held);
+		let bytes: usize = held.iter().map(|packet| packet.data().len()).sum();
+		let segments: usize = held.iter().map(|packet| packet.data().len() / 255 + 1).sum();
+		if segments > 255 || bytes > 255 * 255 {
+			// Too much for one page, so ogg splits it after packets
+			// that don't have a granule position. They end no earlier
+			// than the last packet that has one.
+			let last = self.last_granule.unwrap_or(self.page_start);
+			let count = held.len() - 1;
+			for packet in held[..count].iter_mut().filter(|packet| packet.absgp() == u64::MAX) {
+				packet.set_absgp(last)
+			}
+		}
+		// Held packets usually come from one page of the input,
+		// so they fit on a page of their own
+		if self.pending_packets > 0 && (self.pending_segments + segments > 255 || self.pending_bytes + bytes > 255 * 255) {
+			self.flush(writer, summary)?
+		}
+		self.first_granule.get_or_insert(held[0].absgp());
+		for packet in &held {
+			self.output.packet_in(packet)?
+		}
+		self.pending_packets += held.len();
+		self.pending_bytes += bytes;
+		self.pending_segments += segments;
+		self.last_bytes = held.last().unwrap().data().len();
+		if let (Some(granule), Some(last)) = (granule, self.last_granule) {
+			self.last_duration = granule.saturating_sub(last)
+		}
+		self.last_granule = granule;
+
+		// The page is written as soon as it's full, guessing the
+		// next packet is like the last one
+		let full = match pacing {
+			PagePacing::MaxPageBytes(size) => self.pending_bytes + self.last_bytes > usize::from(size),
+			PagePacing::MaxPageGranules(granules) => granule
+				.map(|granule| granule.saturating_sub(self.page_start).saturating_add(self.last_duration) > granules)
+				.unwrap_or(false),
+			PagePacing::OnePacketPerPage => true
+		};
+		if ends_stream || (full && granule.is_some()) {
+			self.flush(writer, summary)?
+		}
+		Ok(())
+	}
+}
+
+/// Copy a physical stream while putting the packets of every
+/// logical stream on new pages, as decided by `pacing`.
+/// 
+/// Header packets are put on pages of their own, as most
+/// codecs need. To keep granule positions right, a page only
+/// ends after a packet with a known granule position. Where a
+/// [Timestamper] can work them out every packet has one,
+/// otherwise only the last packet on every page of the input
+/// does. So `pacing` is followed as closely as these allow.
+/// 
+/// A page is written as soon as the packet that ends it is
+/// read, so pages of different logical streams keep the order
+/// of the packets ending them. Only a page that would be too
+/// large for ogg waits for the next packet to end it.
+pub fn repaginate<R: Read, W: Write>(reader: R, mut writer: W, pacing: PagePacing) -> Result<RepaginateSummary, Error> {
+	let mut pages = PageReader::new(reader)?;
+	let mut streams: HashMap<i32, Repaginator> = HashMap::new();
+	let mut summary = RepaginateSummary::default();
+
+	while let Some((_, mut page)) = pages.next_page()? {
+		summary.pages_read += 1;
+		let serial = page.stream_serial();
+		if page.begins_logical_stream() || !streams.contains_key(&serial) {
+			// A logical stream joined without its first page has no
+			// headers left to keep apart
+			let header_packets = match page.begins_logical_stream() {
+				true => Codec::identify(page.data()).header_packets(page.data()).unwrap_or(1),
+				false => 0
+			};
+			streams.insert(serial, Repaginator {
+				input: Timestamper::new(serial)?,
+				output: Stream::new(serial)?,
+				header_packets,
+				packets: 0,
+				held: vec![],
+				pending_packets: 0,
+				pending_bytes: 0,
+				pending_segments: 0,
+				page_start: 0,
+				first_granule: None,
+				last_granule: Some(0),
+				last_bytes: 0,
+				last_duration: 0
+			});
+		}
+
+		let stream = streams.get_mut(&serial).unwrap();
+		stream.input.page_in(&mut page)?;
+		let packets: Vec<Packet> = stream.input.packets().collect();
+		for packet in packets {
+			stream.packet_in(&mut writer, packet, pacing, &mut summary)?
+		}
+
+		if page.ends_logical_stream() {
+			streams.remove(&serial);
+		}
+	}
+
+	// Logical streams that never ended
+	for stream in streams.values_mut() {
+		for packet in std::mem::take(&mut stream.held) {
+			stream.output.packet_in(&packet)?
+		}
+		stream.flush(&mut writer, &mut summary)?
+	}
+	writer.flush()?;
+
+	Ok(summary)
+}
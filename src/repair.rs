@@ -0,0 +1,416 @@
+use std::{
+	collections::{ HashMap, VecDeque },
+	io::{ Read, Write }
+};
+
+use crate::{ Damage, Error, Page, PageReader, Timestamper };
+
+/// What [repair] or [salvage] changed while copying a physical
+/// stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepairSummary {
+	/// The number of pages read.
+	pub pages_read: u64,
+	/// The number of pages written.
+	pub pages_written: u64,
+	/// The number of pages dropped because nothing in them
+	/// could be recovered.
+	pub pages_dropped: u64,
+	/// The number of pages given a new sequence number.
+	pub pages_renumbered: u64,
+	/// The number of pages that were read with a bad checksum.
+	/// Every written page gets a new checksum either way.
+	pub checksums_fixed: u64,
+	/// The number of logical streams that were missing an end
+	/// of stream flag on their last page.
+	pub end_of_stream_added: u64,
+	/// The number of pages in the middle of a logical stream
+	/// that had an end of stream flag.
+	pub end_of_stream_removed: u64,
+	/// The number of last pages given a granule position
+	/// because they didn't have one.
+	pub granules_fixed: u64,
+	/// The number of those last pages that finish packets of
+	/// unknown duration. They are given the granule position
+	/// of the page before, which is too early.
+	pub granules_unknown: u64,
+	/// The number of packets dropped because part of them
+	/// was lost.
+	pub partial_packets_dropped: u64,
+	/// The damage found while reading.
+	pub damage: Vec<Damage>
+}
+
+/// A page waiting to be written.
+struct Slot {
+	page: Option<Page>,
+	/// Whether the page won't change anymore.
+	settled: bool
+}
+
+/// The repair state of a logical stream.
+struct StreamRepair {
+	/// The sequence number for the next page.
+	next_index: u32,
+	/// The positions of the pages since the last page that
+	/// ended on a packet boundary, including that page. Only
+	/// the first one can have packets that are finished.
+	chain: Vec<u64>,
+	/// The position of the last complete page, which is held
+	/// back in case it turns out to be the last page.
+	held: Option<u64>,
+	/// The granule position of the last page that was settled
+	/// and finished a packet.
+	granule: Option<u64>,
+	/// How long the packets finished on settled pages after
+	/// `granule` are, if every one of them has a known duration.
+	since_granule: Option<u64>,
+	/// How long the packets finished on the held page are.
+	held_duration: Option<u64>,
+	/// Works out how long the packets on every held page are.
+	timestamper: Option<Timestamper>
+}
+
+/// Pages in the order they will be written.
+/// 
+/// Pages are held back until nothing about them can change, and
+/// every page read after a held page waits for it. A page of a
+/// logical stream is only settled once the next one is read, so
+/// in a multiplexed file with a logical stream whose pages are
+/// far apart, everything read in between is queued.
+struct Queue {
+	slots: VecDeque<Slot>,
+	/// The position of the first slot.
+	start: u64
+}
+
+impl Queue {
+	fn push(&mut self, page: Page) -> u64 {
+		self.slots.push_back(Slot { page: Some(page), settled: false });
+		self.start + self.slots.len() as u64 - 1
+	}
+
+	fn slot(&mut self, position: u64) -> &mut Slot {
+		&mut self.slots[(position - self.start) as usize]
+	}
+
+	fn page(&mut self, position: u64) -> &mut Page {
+		self.slot(position).page.as_mut().expect("page should not be dropped yet")
+	}
+
+	/// Write every page at the front of the queue that is settled.
+	fn write<W: Write>(&mut self, writer: &mut W, summary: &mut RepairSummary) -> Result<(), Error> {
+		while self.slots.front().map(|slot| slot.settled).unwrap_or(false) {
+			let slot = self.slots.pop_front().unwrap();
+			self.start += 1;
+			if let Some(page) = slot.page {
+				writer.write_all(page.header())?;
+				writer.write_all(page.data())?;
+				summary.pages_written += 1;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Check whether the last packet on a page goes on to the next page.
+fn ends_open(page: &Page) -> bool {
+	page.lacing_values().last() == Some(&255)
+}
+
+/// Drop the segments at the start of a page that continue a
+/// packet whose beginning was lost.
+/// 
+/// Returns `false` if nothing is left of the page.
+fn drop_leading_partial(page: &mut Page) -> bool {
+	let lacing_values = page.lacing_values();
+	let end = match lacing_values.iter().position(|value| *value < 255) {
+		None => return false,
+		Some(end) => end + 1
+	};
+	if end == lacing_values.len() { return false }
+
+	let bytes = lacing_values[..end].iter().map(|value| *value as usize).sum::<usize>();
+	let lacing_values = lacing_values[end..].to_vec();
+	let data = page.data()[bytes..].to_vec();
+	page.set_segments(&lacing_values, data);
+	page.set_continues_packet(false);
+	true
+}
+
+/// Drop the segments at the end of a page that belong to a
+/// packet whose end was lost.
+/// 
+/// Returns `false` if nothing is left of the page.
+fn drop_trailing_partial(page: &mut Page) -> bool {
+	let lacing_values = page.lacing_values();
+	let end = match lacing_values.iter().rposition(|value| *value < 255) {
+		None => return false,
+		Some(end) => end + 1
+	};
+
+	let bytes = lacing_values[..end].iter().map(|value| *value as usize).sum::<usize>();
+	let lacing_values = lacing_values[..end].to_vec();
+	let data = page.data()[..bytes].to_vec();
+	page.set_segments(&lacing_values, data);
+	true
+}
+
+/// Copies a physical stream while repairing it.
+struct Repair {
+	queue: Queue,
+	streams: HashMap<i32, StreamRepair>,
+	summary: RepairSummary,
+	/// Whether pages in the middle of a logical stream are fixed
+	/// too, or only the end of every logical stream.
+	rewrite: bool
+}
+
+impl Repair {
+	fn new(rewrite: bool) -> Self {
+		Self {
+			queue: Queue { slots: VecDeque::new(), start: 0 },
+			streams: HashMap::new(),
+			summary: RepairSummary::default(),
+			rewrite
+		}
+	}
+
+	/// Mark a page as settled, clearing an end of stream flag
+	/// unless it is the last page.
+	fn settle(&mut self, position: u64, last: bool) {
+		let page = self.queue.page(position);
+		if self.rewrite && !last && page.ends_logical_stream() {
+			page.set_ends_logical_stream(false);
+			self.summary.end_of_stream_removed += 1;
+		}
+		page.set_crc_checksum();
+		self.queue.slot(position).settled = true;
+	}
+
+	fn drop_page(&mut self, position: u64) {
+		let slot = self.queue.slot(position);
+		slot.page = None;
+		slot.settled = true;
+		self.summary.pages_dropped += 1;
+	}
+
+	/// Hold back a complete page, settling the one held before.
+	fn hold(&mut self, serial: i32, position: u64) {
+		let mut page = self.queue.page(position).clone();
+		let stream = self.streams.get_mut(&serial).expect("stream should exist");
+		let duration = stream.timestamper.as_mut().and_then(|timestamper| {
+			timestamper.page_in(&mut page).ok()?;
+			timestamper.packets().for_each(drop);
+			timestamper.page_duration()
+		});
+		let previous_duration = std::mem::replace(&mut stream.held_duration, duration);
+		let previous = stream.held.replace(position);
+
+		if let Some(previous) = previous {
+			let page = self.queue.page(previous);
+			let stream = self.streams.get_mut(&serial).unwrap();
+			if page.finished_packets() > 0 && page.absgp() != u64::MAX {
+				stream.granule = Some(page.absgp());
+				stream.since_granule = Some(0)
+			} else {
+				stream.since_granule = stream.since_granule.zip(previous_duration).map(|(since, duration)| since + duration)
+			}
+			self.settle(previous, false)
+		}
+	}
+
+	/// Settle the pages since the last complete page. If the last
+	/// packet on them is never finished, drop that packet.
+	/// 
+	/// When only the end of streams is fixed, the packet is only
+	/// dropped if `end` is set.
+	fn close_chain(&mut self, serial: i32, end: bool) {
+		let stream = self.streams.get_mut(&serial).expect("stream should exist");
+		let chain = std::mem::take(&mut stream.chain);
+		let (last, rest) = match chain.split_last() {
+			None => return,
+			Some(split) => split
+		};
+
+		if !ends_open(self.queue.page(*last)) || (!self.rewrite && !end) {
+			for position in rest {
+				self.hold(serial, *position)
+			}
+			return self.hold(serial, *last)
+		}
+
+		// Everything after the first page only has parts of the
+		// unfinished packet
+		let first = chain[0];
+		for position in chain[1..].iter().rev() {
+			self.drop_page(*position);
+			let stream = self.streams.get_mut(&serial).unwrap();
+			stream.next_index = stream.next_index.wrapping_sub(1);
+		}
+		self.summary.partial_packets_dropped += 1;
+
+		if drop_trailing_partial(self.queue.page(first)) {
+			self.hold(serial, first)
+		} else {
+			self.drop_page(first);
+			let stream = self.streams.get_mut(&serial).unwrap();
+			stream.next_index = stream.next_index.wrapping_sub(1);
+		}
+	}
+
+	/// Add a page read from the input.
+	fn page_in(&mut self, mut page: Page) {
+		self.summary.pages_read += 1;
+		if !page.verify_crc_checksum() {
+			self.summary.checksums_fixed += 1
+		}
+
+		let serial = page.stream_serial();
+		if page.begins_logical_stream() && self.streams.contains_key(&serial) {
+			// The same serial number starts over, in a new link
+			// of a chained stream
+			self.finish(serial)
+		}
+		let stream = self.streams.entry(serial).or_insert_with(|| StreamRepair {
+			next_index: page.index(),
+			chain: vec![],
+			held: None,
+			granule: None,
+			since_granule: Some(0),
+			held_duration: None,
+			timestamper: Timestamper::new(serial).ok()
+		});
+		let open = match stream.chain.last() {
+			None => false,
+			Some(position) => ends_open(self.queue.page(*position))
+		};
+
+		if self.rewrite && page.continues_packet() && !open {
+			self.summary.partial_packets_dropped += 1;
+			if !drop_leading_partial(&mut page) {
+				self.summary.pages_dropped += 1;
+				return
+			}
+		}
+
+		let continues_chain = open && page.continues_packet();
+		if !continues_chain {
+			self.close_chain(serial, false);
+		}
+
+		let stream = self.streams.get_mut(&serial).unwrap();
+		if self.rewrite && page.index() != stream.next_index {
+			page.set_index(stream.next_index);
+			self.summary.pages_renumbered += 1;
+		}
+		stream.next_index = page.index().wrapping_add(1);
+
+		let position = self.queue.push(page);
+		if continues_chain && self.queue.page(position).finished_packets() > 0 {
+			// The unfinished packet is finished, so the pages
+			// before this one are complete
+			let stream = self.streams.get_mut(&serial).unwrap();
+			let chain = std::mem::replace(&mut stream.chain, vec![position]);
+			for previous in chain {
+				self.hold(serial, previous)
+			}
+		} else {
+			self.streams.get_mut(&serial).unwrap().chain.push(position)
+		}
+	}
+
+	/// Close a logical stream, making its last complete page the end.
+	fn finish(&mut self, serial: i32) {
+		self.close_chain(serial, true);
+		let stream = self.streams.remove(&serial).expect("stream should exist");
+
+		if let Some(position) = stream.held {
+			let page = self.queue.page(position);
+			if page.finished_packets() == 0 || page.absgp() == u64::MAX {
+				let mut granule = stream.granule.unwrap_or(0);
+				if page.finished_packets() > 0 {
+					// Count forward over the packets since the last
+					// known granule position
+					match stream.since_granule.zip(stream.held_duration) {
+						Some((since, duration)) => granule += since + duration,
+						None => self.summary.granules_unknown += 1
+					}
+				}
+				if page.absgp() != granule {
+					page.set_absgp(granule);
+					self.summary.granules_fixed += 1;
+				}
+			}
+			if !page.ends_logical_stream() {
+				page.set_ends_logical_stream(true);
+				self.summary.end_of_stream_added += 1;
+			}
+			self.settle(position, true)
+		}
+	}
+
+	/// Read every page from `pages`, then close every logical
+	/// stream that is still open.
+	fn run<R: Read, W: Write>(mut self, mut pages: PageReader<R>, mut writer: W) -> Result<RepairSummary, Error> {
+		while let Some((_, page)) = pages.next_page()? {
+			self.page_in(page);
+			self.queue.write(&mut writer, &mut self.summary)?;
+		}
+
+		let mut serials: Vec<i32> = self.streams.keys().copied().collect();
+		serials.sort_unstable();
+		for serial in serials {
+			self.finish(serial)
+		}
+		self.queue.write(&mut writer, &mut self.summary)?;
+		writer.flush()?;
+
+		self.summary.damage = pages.take_damage();
+		Ok(self.summary)
+	}
+}
+
+/// Copy a physical stream from `reader` to `writer`, fixing
+/// as much as possible on the way.
+/// 
+/// - Pages with a bad checksum are kept, and every page gets
+///   a new checksum.
+/// - Pages of every logical stream are numbered without gaps.
+/// - Packets that were partly lost are dropped.
+/// - The last page of every logical stream is given the end
+///   of stream flag, and a granule position if it doesn't
+///   have one.
+/// 
+/// A last page that finishes packets but has no granule position
+/// is given one from how long its packets are, for the codecs
+/// [Timestamper] knows.
+/// 
+/// Bytes that aren't part of any page are dropped. Pages are
+/// written in the order they are read, so the last page read of
+/// every logical stream is held in memory until the next one is
+/// read, along with every page read after it. In a multiplexed
+/// file with a logical stream whose pages are far apart, that
+/// can be everything in between.
+pub fn repair<R: Read, W: Write>(reader: R, writer: W) -> Result<RepairSummary, Error> {
+	let mut pages = PageReader::new(reader)?;
+	pages.set_keep_bad_checksums(true);
+	Repair::new(true).run(pages, writer)
+}
+
+/// Copy a physical stream from `reader` to `writer`, closing
+/// every logical stream that was cut off.
+/// 
+/// This is meant for files that were cut off, for example by a
+/// crash while recording. Pages are copied as they are, up to
+/// the last complete page of every logical stream. A packet
+/// that is unfinished on that page is dropped, and the page
+/// is given the end of stream flag and the granule position
+/// of its last complete packet.
+/// 
+/// Unlike [repair], pages with a bad checksum are dropped and
+/// nothing is fixed in the middle of a logical stream.
+pub fn salvage<R: Read, W: Write>(reader: R, writer: W) -> Result<RepairSummary, Error> {
+	Repair::new(false).run(PageReader::new(reader)?, writer)
+}
@@ -0,0 +1,199 @@
+use std::{
+	collections::{ HashMap, HashSet },
+	io::{ Read, Write }
+};
+
+use crate::{ Codec, Comments, Error, Page, PageReader, Stream };
+
+/// A logical stream in a [Link].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkStream {
+	pub serial: i32,
+	pub codec: Codec,
+	/// The comments of the logical stream, if it has any.
+	pub comments: Option<Comments>
+}
+
+/// One link of a chained physical stream: a group of logical
+/// streams that begin together, followed by their data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+	/// The index of the link in the physical stream.
+	pub index: usize,
+	/// The offset of the first page of the link.
+	pub offset: u64,
+	pub streams: Vec<LinkStream>
+}
+
+impl Link {
+	/// Return the value of the first comment field called `name`
+	/// in any logical stream of the link, ignoring case.
+	pub fn comment(&self, name: &str) -> Option<&str> {
+		self.streams.iter()
+			.filter_map(|stream| stream.comments.as_ref())
+			.find_map(|comments| comments.get(name))
+	}
+
+	/// Return a file name for this link.
+	/// 
+	/// The name is made from the index of the link along with the
+	/// `ARTIST` and `TITLE` comments, if there are any, and an
+	/// extension that fits the codecs in the link.
+	pub fn file_name(&self) -> String {
+		let mut name = format!("{:02}", self.index + 1);
+		for field in ["ARTIST", "TITLE"] {
+			if let Some(value) = self.comment(field) {
+				let value: String = value.chars()
+					.map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+					.collect();
+				let value = value.trim();
+				if !value.is_empty() {
+					name.push_str(" - ");
+					name.push_str(value)
+				}
+			}
+		}
+
+		let codecs: Vec<Codec> = self.streams.iter().map(|stream| stream.codec).collect();
+		let extension = if codecs.contains(&Codec::Theora) { "ogv" }
+			else if codecs.iter().all(|codec| *codec == Codec::Opus) && !codecs.is_empty() { "opus" }
+			else { "ogg" };
+		format!("{}.{}", name, extension)
+	}
+}
+
+/// Decodes the header packets of a logical stream in a link.
+struct HeaderState {
+	stream: Stream,
+	packets: usize,
+	header_packets: Option<usize>,
+	ended: bool
+}
+
+impl HeaderState {
+	/// Check whether every header that might have comments was read.
+	fn done(&self, stream: &LinkStream) -> bool {
+		self.ended || stream.comments.is_some() || self.packets >= self.header_packets.unwrap_or(2)
+	}
+}
+
+/// Split a chained physical stream into its links.
+/// 
+/// A new link starts with a beginning of stream page, after
+/// every logical stream of the previous link has ended. For
+/// every link, `create` is called to return the writer for it,
+/// and every page of the link is written there as it is.
+/// 
+/// Pages are held in memory until the comments of the link are
+/// read, so `create` can use them, for example through
+/// [Link::file_name]:
+/// 
+/// ```rust,no_run
+/// # use std::{ fs::File, path::Path };
+/// # use ogg_xiph::split_links;
+/// # let directory = Path::new("");
+/// let links = split_links(File::open("radio.ogg")?, |link| {
+/// 	File::create(directory.join(link.file_name()))
+/// })?;
+/// # Ok::<(), ogg_xiph::Error>(())
+/// ```
+/// 
+/// Returns every link that was written.
+pub fn split_links<R, W, F>(reader: R, mut create: F) -> Result<Vec<Link>, Error>
+where
+	R: Read,
+	W: Write,
+	F: FnMut(&Link) -> std::io::Result<W>
+{
+	let mut pages = PageReader::new(reader)?;
+	let mut links = vec![];
+	let mut link: Option<Link> = None;
+	let mut headers: HashMap<i32, HeaderState> = HashMap::new();
+	let mut open: HashSet<i32> = HashSet::new();
+	let mut buffered: Vec<Page> = vec![];
+	let mut writer: Option<W> = None;
+
+	while let Some((offset, mut page)) = pages.next_page()? {
+		let serial = page.stream_serial();
+
+		if page.begins_logical_stream() && open.is_empty() {
+			if let Some(link) = link.take() {
+				finish_link(&link, &mut create, writer.take(), &buffered)?;
+				buffered.clear();
+				headers.clear();
+				links.push(link)
+			}
+		}
+		let current = link.get_or_insert_with(|| Link { index: links.len(), offset, streams: vec![] });
+
+		if page.begins_logical_stream() {
+			open.insert(serial);
+			current.streams.push(LinkStream { serial, codec: Codec::Unknown, comments: None });
+			headers.insert(serial, HeaderState {
+				stream: Stream::new(serial)?,
+				packets: 0,
+				header_packets: None,
+				ended: false
+			});
+		}
+		if page.ends_logical_stream() {
+			open.remove(&serial);
+		}
+
+		if let Some(writer) = &mut writer {
+			writer.write_all(page.header())?;
+			writer.write_all(page.data())?;
+			continue
+		}
+
+		if let Some(state) = headers.get_mut(&serial) {
+			if state.stream.page_in(&mut page).is_ok() {
+				let stream = current.streams.iter_mut().find(|stream| stream.serial == serial).unwrap();
+				let packets: Vec<_> = state.stream.packets().flatten().collect();
+				for packet in packets {
+					if state.packets == 0 {
+						stream.codec = Codec::identify(packet.data());
+						state.header_packets = stream.codec.header_packets(packet.data());
+					} else if stream.comments.is_none() && !state.done(stream) {
+						stream.comments = Comments::parse(stream.codec, packet.data());
+					}
+					state.packets += 1
+				}
+			}
+			state.ended |= page.ends_logical_stream();
+		}
+		buffered.push(page);
+
+		let ready = current.streams.iter().all(|stream| headers[&stream.serial].done(stream));
+		if ready {
+			let mut new_writer = create(current)?;
+			for page in buffered.drain(..) {
+				new_writer.write_all(page.header())?;
+				new_writer.write_all(page.data())?;
+			}
+			writer = Some(new_writer)
+		}
+	}
+
+	if let Some(link) = link.take() {
+		finish_link(&link, &mut create, writer.take(), &buffered)?;
+		links.push(link)
+	}
+
+	Ok(links)
+}
+
+/// Write the pages of a link that are still held in memory
+/// and flush its writer.
+fn finish_link<W: Write, F: FnMut(&Link) -> std::io::Result<W>>(link: &Link, create: &mut F, writer: Option<W>, buffered: &[Page]) -> Result<(), Error> {
+	let mut writer = match writer {
+		Some(writer) => writer,
+		None => create(link)?
+	};
+	for page in buffered {
+		writer.write_all(page.header())?;
+		writer.write_all(page.data())?;
+	}
+	writer.flush()?;
+	Ok(())
+}
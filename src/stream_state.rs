@@ -1,11 +1,13 @@
 use std::{
+	ffi::c_void,
+	io::IoSlice,
 	mem::MaybeUninit,
 	num::NonZeroUsize,
-	os::raw::c_int
+	os::raw::{ c_int, c_long }
 };
 use ogg_next_sys::*;
 
-use crate::{ Packet, Page, InternalError };
+use crate::{ Packet, PacketRef, Page, PagePolicy, PendingPackets, InternalError };
 
 /// This struct is responsible for managing the current encode
 /// and decode state of a logical stream.
@@ -20,42 +22,151 @@ pub struct Stream {
 	/// Keep track of whetther or not the stream has at least
 	/// one page submitted to process.
 	has_pages: bool,
-	/// Page data. This points to data **owned by ogg** and we
-	/// should be careful with it.
+	/// The last page taken out of the stream, copied from the
+	/// buffers of ogg.
 	page_buffer: Option<Page>,
-	/// Packet data. This points to data **owned by ogg** and we
-	/// should be careful with it.
-	packet_buffer: Option<Packet>
+	/// Decides when [page_out](Stream::page_out) flushes pages.
+	policy: Option<Box<dyn PagePolicy + Send>>,
+	pending: PendingPackets,
+	/// Whether the policy asked for the pending packets to be
+	/// flushed.
+	flush_due: bool
 }
 
 impl Stream {
 	/// Return an initialized `Stream`.
-	pub fn new(serial: i32) -> Result<Self, ()> {
+	pub fn new(serial: i32) -> Result<Self, InternalError> {
 		let mut stream_state: MaybeUninit<ogg_stream_state> = MaybeUninit::uninit();
 		let code = unsafe {
 			ogg_stream_init(stream_state.as_mut_ptr(), serial as c_int)
 		};
 		if code == 0 {
-			Ok(Self { stream_state: unsafe { stream_state.assume_init() }, has_pages: false, page_buffer: None, packet_buffer: None })
-		} else { Err(()) }
+			Ok(Self {
+				stream_state: unsafe { stream_state.assume_init() },
+				has_pages: false,
+				page_buffer: None,
+				policy: None,
+				pending: PendingPackets::default(),
+				flush_due: false
+			})
+		} else { Err(InternalError("ogg_stream_init".to_string())) }
 	}
 
 	/// Reset this `Stream` back to an initial state.
-	pub fn reset(&mut self) -> Result<(), ()> {
+	pub fn reset(&mut self) -> Result<(), InternalError> {
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		match unsafe {
 			ogg_stream_reset(&mut self.stream_state as *mut ogg_stream_state)
 		} {
-			0 => { self.has_pages = false; Ok(()) },
-			_ => Err(())
+			0 => {
+				self.has_pages = false;
+				self.pending = PendingPackets::default();
+				self.flush_due = false;
+				Ok(())
+			},
+			_ => Err(InternalError("ogg_stream_reset".to_string()))
 		}
 	}
 
+	/// Continue a logical stream that was started somewhere
+	/// else, such as one that is already written to a file.
+	/// 
+	/// `page_index` is the sequence number of the next page,
+	/// `packets` the number of packets in the stream so far and
+	/// `absgp` the granule position of the last one. The next
+	/// page won't begin the logical stream.
+	/// 
+	/// Call this before submitting any packets.
+	pub fn resume(&mut self, page_index: u32, packets: u64, absgp: u64) {
+		self.page_buffer = None;
+		self.set_position(page_index, packets, absgp);
+		self.pending.page_granule = Some(absgp);
+		self.pending.granule = Some(absgp);
+	}
+
+	/// Set where ogg continues the logical stream from.
+	/// 
+	/// libogg has no function for this, so the fields of the
+	/// [ogg_stream_state] are written directly, which is the only
+	/// place they are. They only take effect on a stream without
+	/// buffered packets:
+	/// - `b_o_s` is set once the first page is out, so the next
+	///   page doesn't get the beginning of stream flag.
+	/// - `e_o_s` is cleared, so more packets can be added.
+	/// - `pageno` is the sequence number of the next page.
+	/// - `packetno` is the number of the next packet.
+	/// - `granulepos` is the granule position of the last page.
+	fn set_position(&mut self, page_index: u32, packets: u64, absgp: u64) {
+		debug_assert!(self.stream_state.body_fill == 0 && self.stream_state.lacing_fill == 0, "the stream should have no packets");
+		self.stream_state.b_o_s = 1;
+		self.stream_state.e_o_s = 0;
+		self.stream_state.pageno = page_index as c_long;
+		self.stream_state.packetno = packets as i64;
+		self.stream_state.granulepos = absgp as i64;
+	}
+
+	/// Set the [PagePolicy] that decides when [page_out](Stream::page_out)
+	/// puts packets on pages, in place of the default of ogg.
+	pub fn set_page_policy<P: PagePolicy + Send + 'static>(&mut self, policy: P) {
+		self.policy = Some(Box::new(policy));
+		self.flush_due = false
+	}
+
+	/// Go back to the default of ogg for putting packets on pages.
+	pub fn remove_page_policy(&mut self) {
+		self.policy = None;
+		self.flush_due = false
+	}
+
+	/// Return what was added to the `Stream` and isn't on a page yet.
+	pub fn pending(&self) -> &PendingPackets {
+		&self.pending
+	}
+
+	/// Keep track of a packet added to the stream, and ask the
+	/// policy whether to flush.
+	fn pending_in(&mut self, bytes: usize, absgp: u64) {
+		self.pending.packet_in(bytes, absgp);
+		if let Some(policy) = &mut self.policy {
+			self.flush_due |= policy.flush(&self.pending)
+		}
+	}
+
+	/// Keep the page that ogg returned and track what is left.
+	/// 
+	/// # Safety
+	/// 
+	/// `page` must be returned by ogg from this stream.
+	unsafe fn take_page(&mut self, page: ogg_page) -> &Page {
+		let page = unsafe { Page::try_from(page) }.expect("page returned from ogg should be valid");
+		let remaining = self.stream_state.lacing_fill as usize;
+		self.pending.page_out(page.finished_packets() as usize, page.data().len(), page.absgp(), remaining);
+		if remaining == 0 { self.flush_due = false }
+		self.page_buffer.insert(page)
+	}
+
+	/// Check whether ogg has run into an internal error.
+	/// 
+	/// Several ogg functions return the same value both for
+	/// "not enough data" and for internal errors, this tells
+	/// them apart.
+	fn internal_error(&mut self) -> bool {
+		unsafe { ogg_stream_check(&mut self.stream_state as *mut ogg_stream_state) != 0 }
+	}
+
+	/// Return the error for ogg returning no packet.
+	fn no_packet(&mut self) -> PacketOutError {
+		if self.internal_error() { PacketOutError::InternalError } else { PacketOutError::NeedMoreData }
+	}
+
+	/// Return the error for ogg returning no page.
+	fn no_page(&mut self) -> PageOutError {
+		if self.internal_error() { PageOutError::InternalError } else { PageOutError::NeedMoreData }
+	}
+
 	/// Check if the `Stream` has ended, possibly due to error.
 	pub fn end_of_stream(&mut self) -> bool {
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		match unsafe {
 			ogg_stream_eos(&mut self.stream_state as *mut ogg_stream_state)
 		} {
@@ -68,7 +179,6 @@ impl Stream {
 	/// Add a `Page` to the `Stream`.
 	pub fn page_in(&mut self, page: &mut Page) -> Result<(), PageInError> {
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		if page.stream_serial() != self.stream_state.serialno as c_int {
 			return Err(PageInError::WrongSerial((self.stream_state.serialno as c_int, page.stream_serial())))
 		}
@@ -76,7 +186,7 @@ impl Stream {
 		unsafe {
 			let page = page.ogg_page();
 			match ogg_stream_pagein(&mut self.stream_state as *mut ogg_stream_state, page as *mut ogg_page) {
-				-1 => Err(PageInError::InternalError("ogg_stream_pagein".to_string())),
+				-1 => Err(PageInError::InternalError(InternalError("ogg_stream_pagein".to_string()))),
 				0 => { self.has_pages = true; Ok(()) },
 				unexpected => panic!("ogg_stream_pagein should always return 0 or -1 but returned {}", unexpected)
 			}
@@ -86,9 +196,12 @@ impl Stream {
 	/// Export a packet from the `Stream`.
 	/// 
 	/// This should be run *after* submitting at least one `Page` to the stream.
-	pub fn packet_out(&mut self) -> Result<&Packet, PacketOutError> {
+	/// 
+	/// The returned [PacketRef] borrows data owned by ogg, so it
+	/// has to be dropped (or copied with [PacketRef::to_packet])
+	/// before the `Stream` can be used again.
+	pub fn packet_out(&mut self) -> Result<PacketRef<'_>, PacketOutError> {
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		if !self.has_pages {
 			return Err(PacketOutError::NoPages)
 		}
@@ -98,14 +211,11 @@ impl Stream {
 		unsafe {
 			match ogg_stream_packetout(&mut self.stream_state as *mut ogg_stream_state, packet.as_mut_ptr()) {
 				-1 => Err(PacketOutError::OutOfSync),
-				0 => Err(PacketOutError::InternalError),
-				1 => {
-					self.packet_buffer = Some(
-						Packet::try_from(packet.assume_init())
-							.expect("packet returned from ogg_stream_packetout should be valid")
-					);
-					Ok(self.packet_buffer.as_ref().unwrap())
-				},
+				0 => Err(self.no_packet()),
+				1 => Ok(
+					PacketRef::try_from(packet.assume_init())
+						.expect("packet returned from ogg_stream_packetout should be valid")
+				),
 				unexpected => panic!("ogg_stream_packetout should always return 0 or -1 but returned {}", unexpected)
 			}
 		}
@@ -114,11 +224,10 @@ impl Stream {
 	/// Peek the next `Packet` in the `Stream` without advancing decoding.
 	/// 
 	/// This should be run *after* submitting at least one `Page` to the stream.
-	pub fn packet_peek(&mut self) -> Result<&Packet, PacketOutError> {
+	pub fn packet_peek(&mut self) -> Result<PacketRef<'_>, PacketOutError> {
 		// Packet data is owned by ogg
 		// https://xiph.org/ogg/doc/libogg/ogg_stream_packetout.html
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		if !self.has_pages {
 			return Err(PacketOutError::NoPages)
 		}
@@ -128,66 +237,125 @@ impl Stream {
 		unsafe {
 			match ogg_stream_packetpeek(&mut self.stream_state as *mut ogg_stream_state, packet.as_mut_ptr()) {
 				-1 => Err(PacketOutError::OutOfSync),
-				0 => Err(PacketOutError::InternalError),
-				1 => {
-					self.packet_buffer = Some(
-						Packet::try_from(packet.assume_init())
-							.expect("packet returned from ogg_stream_packetpeek should be valid")
-					);
-					Ok(self.packet_buffer.as_ref().unwrap())
-				},
+				0 => Err(self.no_packet()),
+				1 => Ok(
+					PacketRef::try_from(packet.assume_init())
+						.expect("packet returned from ogg_stream_packetpeek should be valid")
+				),
 				unexpected => panic!("ogg_stream_packetpeek should always return 0 or -1 but returned {}", unexpected)
 			}
 		}
 	}
 
+	/// Return an iterator taking every complete [Packet] out of
+	/// the `Stream`.
+	/// 
+	/// Holes in the data are returned as [PacketOutError::OutOfSync]
+	/// without ending the iterator. It ends when more pages are
+	/// needed to complete a packet.
+	pub fn packets(&mut self) -> Packets<'_> {
+		Packets { stream: self, done: false }
+	}
+
 	/// Add a `Packet` to the `Stream`.
-	pub fn packet_in(&mut self, packet: &mut Packet) -> Result<(), InternalError> {
+	/// 
+	/// The data, granule position and end of stream flag of
+	/// the packet are copied into the stream as they are when
+	/// this is called. The packet number is assigned by ogg.
+	pub fn packet_in(&mut self, packet: &Packet) -> Result<(), InternalError> {
 		self.page_buffer = None;
-		self.packet_buffer = None;
+		let (bytes, absgp) = (packet.data().len(), packet.absgp());
+		let mut packet = packet.ogg_packet();
 		unsafe {
-			match ogg_stream_packetin(&mut self.stream_state as *mut ogg_stream_state, &mut packet.packet as *mut ogg_packet) {
+			match ogg_stream_packetin(&mut self.stream_state as *mut ogg_stream_state, &mut packet as *mut ogg_packet) {
 				-1 => Err(InternalError("ogg_stream_packetin".to_string())),
-				0 => { self.has_pages = true; Ok(()) },
+				0 => { self.has_pages = true; self.pending_in(bytes, absgp); Ok(()) },
 				unexpected => panic!("ogg_stream_packetin should always return 0 or -1 but returned {}", unexpected)
 			}
 		}
 	}
 
+	/// Add a packet made of several buffers to the `Stream`.
+	/// 
+	/// The buffers are copied into the stream one after another
+	/// as a single packet, so there is no need to join them into
+	/// one [Packet] first.
+	pub fn packet_in_vectored(&mut self, bufs: &[IoSlice<'_>], absgp: u64, end_of_stream: bool) -> Result<(), InternalError> {
+		self.page_buffer = None;
+		let mut iovecs: Vec<ogg_iovec_t> = bufs.iter()
+			.map(|buf| ogg_iovec_t { iov_base: buf.as_ptr() as *mut c_void, iov_len: buf.len() })
+			.collect();
+		let count = match c_int::try_from(iovecs.len()) {
+			Err(_) => return Err(InternalError("ogg_stream_iovecin".to_string())),
+			Ok(count) => count
+		};
+
+		unsafe {
+			match ogg_stream_iovecin(
+				&mut self.stream_state as *mut ogg_stream_state,
+				iovecs.as_mut_ptr(),
+				count,
+				if end_of_stream { 1 } else { 0 },
+				absgp as i64
+			) {
+				-1 => Err(InternalError("ogg_stream_iovecin".to_string())),
+				0 => {
+					self.has_pages = true;
+					self.pending_in(bufs.iter().map(|buf| buf.len()).sum(), absgp);
+					Ok(())
+				},
+				unexpected => panic!("ogg_stream_iovecin should always return 0 or -1 but returned {}", unexpected)
+			}
+		}
+	}
+
 	/// Export a `Page` from the `Stream`.
-	pub fn page_out(&mut self) -> Result<&Page, PacketOutError> {
+	/// 
+	/// If a [PagePolicy] is set, it decides when pages come out.
+	/// Otherwise ogg does.
+	pub fn page_out(&mut self) -> Result<&Page, PageOutError> {
 		// Page is owned by ogg
 		// https://xiph.org/ogg/doc/libogg/ogg_stream_pageout.html
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		let mut page: MaybeUninit<ogg_page> = MaybeUninit::uninit();
+		let state = &mut self.stream_state as *mut ogg_stream_state;
 
 		unsafe {
-			match ogg_stream_pageout(&mut self.stream_state as *mut ogg_stream_state, page.as_mut_ptr()) {
-				0 => Err(PacketOutError::InternalError),
-				_ => {
-					self.page_buffer = Some(Page::try_from(page.assume_init()).expect("packet returned from ogg_stream_pageout should be valid"));
-					Ok(self.page_buffer.as_ref().unwrap())
+			let code = match &self.policy {
+				None => ogg_stream_pageout(state, page.as_mut_ptr()),
+				Some(_) if self.flush_due => ogg_stream_flush(state, page.as_mut_ptr()),
+				Some(policy) => {
+					let size = policy.page_size().map(usize::from).unwrap_or(4096);
+					ogg_stream_pageout_fill(state, page.as_mut_ptr(), size.min(c_int::MAX as usize) as c_int)
 				}
+			};
+			match code {
+				0 => Err(self.no_page()),
+				_ => Ok(self.take_page(page.assume_init()))
 			}
 		}
 	}
 
+	/// Return an iterator taking every complete [Page] out of
+	/// the `Stream`, as decided by [page_out](Stream::page_out).
+	/// 
+	/// Use [page_flush](Stream::page_flush) afterwards to get the
+	/// remaining packets, for example at the end of the stream.
+	pub fn pages(&mut self) -> Pages<'_> {
+		Pages { stream: self, done: false }
+	}
+
 	/// Export a `Page` from the `Stream` with at most the given size in bytes.
-	pub fn page_out_with_max_size(&mut self, size: NonZeroUsize) -> Result<&Page, PacketOutError> {
+	pub fn page_out_with_max_size(&mut self, size: NonZeroUsize) -> Result<&Page, PageOutError> {
 		// Page is owned by us
 		// https://xiph.org/ogg/doc/libogg/ogg_stream_pageout_fill.html
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		let mut page: MaybeUninit<ogg_page> = MaybeUninit::uninit();
 
 		unsafe {
 			match ogg_stream_pageout_fill(&mut self.stream_state as *mut ogg_stream_state, page.as_mut_ptr(), usize::from(size) as c_int) {
-				0 => Err(PacketOutError::InternalError),
-				_ => {
-					self.page_buffer = Some(Page::try_from(page.assume_init()).expect("packet returned from ogg_stream_pageout should be valid"));
-					Ok(self.page_buffer.as_ref().unwrap())
-				}
+				0 => Err(self.no_page()),
+				_ => Ok(self.take_page(page.assume_init()))
 			}
 		}
 	}
@@ -203,20 +371,16 @@ impl Stream {
 	/// 
 	/// This can be used to verify that the stream has no
 	/// more packets to flush.
-	pub fn page_flush(&mut self) -> Result<&Page, PacketOutError> {
+	pub fn page_flush(&mut self) -> Result<&Page, PageOutError> {
 		// Page is owned by us
 		// https://xiph.org/ogg/doc/libogg/ogg_stream_flush.html
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		let mut page: MaybeUninit<ogg_page> = MaybeUninit::uninit();
 
 		unsafe {
 			match ogg_stream_flush(&mut self.stream_state as *mut ogg_stream_state, page.as_mut_ptr()) {
-				0 => Err(PacketOutError::InternalError),
-				_ => {
-					self.page_buffer = Some(Page::try_from(page.assume_init()).expect("packet returned from ogg_stream_pageout should be valid"));
-					Ok(self.page_buffer.as_ref().unwrap())
-				}
+				0 => Err(self.no_page()),
+				_ => Ok(self.take_page(page.assume_init()))
 			}
 		}
 	}
@@ -233,25 +397,25 @@ impl Stream {
 	/// 
 	/// This can be used to verify that the stream has no
 	/// more packets to flush.
-	pub fn page_flush_with_max_size(&mut self, size: NonZeroUsize) -> Result<&Page, PacketOutError> {
+	pub fn page_flush_with_max_size(&mut self, size: NonZeroUsize) -> Result<&Page, PageOutError> {
 		// Page is owned by us
 		// https://xiph.org/ogg/doc/libogg/ogg_stream_flush_fill.html
 		self.page_buffer = None;
-		self.packet_buffer = None;
 		let mut page: MaybeUninit<ogg_page> = MaybeUninit::uninit();
 
 		unsafe {
 			match ogg_stream_flush_fill(&mut self.stream_state as *mut ogg_stream_state, page.as_mut_ptr(), usize::from(size) as c_int) {
-				0 => Err(PacketOutError::InternalError),
-				_ => {
-					self.page_buffer = Some(Page::try_from(page.assume_init()).expect("packet returned from ogg_stream_pageout should be valid"));
-					Ok(self.page_buffer.as_ref().unwrap())
-				}
+				0 => Err(self.no_page()),
+				_ => Ok(self.take_page(page.assume_init()))
 			}
 		}
 	}
 }
 
+// The stream state only points to memory that it owns, and
+// the page buffer owns a copy of its data.
+unsafe impl Send for Stream {}
+
 impl Drop for Stream {
 	fn drop(&mut self) {
 		let code = unsafe {
@@ -262,6 +426,51 @@ impl Drop for Stream {
 	}
 }
 
+/// An iterator over the [Packets](Packet) in a [Stream].
+/// 
+/// See [Stream::packets].
+pub struct Packets<'a> {
+	stream: &'a mut Stream,
+	done: bool
+}
+
+impl Iterator for Packets<'_> {
+	type Item = Result<Packet, PacketOutError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done { return None }
+
+		match self.stream.packet_out() {
+			Ok(packet) => Some(Ok(packet.to_packet())),
+			Err(PacketOutError::NeedMoreData | PacketOutError::NoPages) => { self.done = true; None },
+			Err(PacketOutError::InternalError) => { self.done = true; Some(Err(PacketOutError::InternalError)) },
+			Err(PacketOutError::OutOfSync) => Some(Err(PacketOutError::OutOfSync))
+		}
+	}
+}
+
+/// An iterator over the [Pages](Page) in a [Stream].
+/// 
+/// See [Stream::pages].
+pub struct Pages<'a> {
+	stream: &'a mut Stream,
+	done: bool
+}
+
+impl Iterator for Pages<'_> {
+	type Item = Result<Page, PageOutError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done { return None }
+
+		match self.stream.page_out() {
+			Ok(page) => Some(Ok(page.clone())),
+			Err(PageOutError::NeedMoreData) => { self.done = true; None },
+			Err(PageOutError::InternalError) => { self.done = true; Some(Err(PageOutError::InternalError)) }
+		}
+	}
+}
+
 /// An error returned while adding a page to the stream.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum PageInError {
@@ -271,24 +480,41 @@ pub enum PageInError {
 	/// `(expected serial, actual serial)`
 	WrongSerial ((c_int, i32)),
 	/// An internal error occurred in Ogg.
-	InternalError (String)
+	InternalError (InternalError)
 }
 
 impl std::fmt::Display for PageInError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
 			Self::WrongSerial((expected_serial, actual_serial)) => write!(f, "serial number {} does not match this stream serial {}", actual_serial, expected_serial),
-			Self::InternalError(function) => InternalError::fmt_str(f, function)
+			Self::InternalError(error) => error.fmt(f)
 		}
     }
 }
 
-/// An error returned while adding a page to the stream.
+impl std::error::Error for PageInError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::WrongSerial(_) => None,
+			Self::InternalError(error) => Some(error)
+		}
+	}
+}
+
+/// An error returned while taking a packet out of the stream.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum PacketOutError {
+	/// There is a hole in the data and at least one packet
+	/// was lost. Packets after the hole can still be read.
 	OutOfSync,
+	/// No pages have been added to the stream yet.
 	NoPages,
-	InternalError,
+	/// There is not enough data to complete a packet.
+	/// 
+	/// This is not really an error, add more pages and try again.
+	NeedMoreData,
+	/// An internal error occurred in Ogg.
+	InternalError
 }
 
 impl std::fmt::Display for PacketOutError {
@@ -296,7 +522,33 @@ impl std::fmt::Display for PacketOutError {
         match self {
 			Self::OutOfSync => write!(f, "stream fell out of sync, input might be incomplete"),
 			Self::NoPages => write!(f, "no pages have been submitted to the stream yet"),
-			Self::InternalError => write!(f, "there is not enough data to complete a packet or an internal error occurred")
+			Self::NeedMoreData => write!(f, "there is not enough data to complete a packet"),
+			Self::InternalError => write!(f, "an internal error occurred in the stream")
 		}
     }
 }
+
+impl std::error::Error for PacketOutError {}
+
+/// An error returned while taking a page out of the stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PageOutError {
+	/// There is not enough data to complete a page.
+	/// 
+	/// This is not really an error, add more packets or flush
+	/// the stream and try again.
+	NeedMoreData,
+	/// An internal error occurred in Ogg.
+	InternalError
+}
+
+impl std::fmt::Display for PageOutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::NeedMoreData => write!(f, "there is not enough data to complete a page"),
+			Self::InternalError => write!(f, "an internal error occurred in the stream")
+		}
+    }
+}
+
+impl std::error::Error for PageOutError {}
@@ -4,7 +4,7 @@ use std::{
 	os::raw::c_long
 };
 use ogg_next_sys::*;
-use crate::Page;
+use crate::{ Page, InvalidPage, InternalError };
 
 /// The `SyncState` is responsible for decoding and syncing [Pages](Page).
 /// 
@@ -44,14 +44,14 @@ pub struct SyncState {
 
 impl SyncState {
 		/// Return an initialized `SyncState`.
-		pub fn new() -> Result<Self, ()> {
+		pub fn new() -> Result<Self, InternalError> {
 			let mut sync_state: MaybeUninit<ogg_sync_state> = MaybeUninit::uninit();
 			let code = unsafe {
 				ogg_sync_init(sync_state.as_mut_ptr())
 			};
 			if code == 0 {
 				Ok(Self { sync_state: unsafe { sync_state.assume_init() } })
-			} else { Err(()) }
+			} else { Err(InternalError("ogg_sync_init".to_string())) }
 		}
 
 		/// Reset this `SyncState` to a new state.
@@ -69,6 +69,21 @@ impl SyncState {
 			self.sync_state.unsynced == 0
 		}
 
+		/// Return the bytes written that haven't been returned in
+		/// a page or skipped yet, along with up to `behind` bytes
+		/// right before them.
+		/// 
+		/// ogg keeps returned and skipped bytes in its buffer until
+		/// the next write, so this can look into bytes that were
+		/// just skipped.
+		pub(crate) fn buffered(&self, behind: usize) -> &[u8] {
+			let state = &self.sync_state;
+			if state.data.is_null() { return &[] }
+			let start = (state.returned.max(0) as usize).saturating_sub(behind);
+			let fill = state.fill.max(0) as usize;
+			unsafe { std::slice::from_raw_parts(state.data.add(start), fill.saturating_sub(start)) }
+		}
+
 		/// Provide a buffer for writing to the [ogg_sync_state].
 		fn buffer(&mut self, size: NonZeroUsize) -> &mut [u8] {
 			let buffer = unsafe {
@@ -93,16 +108,19 @@ impl SyncState {
 		}
 
 		/// Write bytes to the `SyncState`.
-		fn write(&mut self, bytes: &[u8]) {
-			let size = NonZeroUsize::try_from(bytes.len())
-				.expect("non zero usize");
+		/// 
+		/// Use [pages](SyncState::pages) afterwards to get the
+		/// [Pages](Page) that could be completed.
+		pub fn write(&mut self, bytes: &[u8]) {
+			let size = match NonZeroUsize::new(bytes.len()) {
+				None => return,
+				Some(size) => size
+			};
 			let buffer = self.buffer(size);
 
 			assert!(buffer.len() >= bytes.len());
 
-			for (index, byte) in bytes.iter().enumerate() {
-				buffer[index] = *byte
-			}
+			buffer[..bytes.len()].copy_from_slice(bytes);
 
 			self.wrote(size);
 		}
@@ -115,50 +133,91 @@ impl SyncState {
 
 			match code {
 				 -1 => Err(PageWriteError::OutOfSync),
-				 0 => Err(PageWriteError::InternalError),
+				 0 => if unsafe { ogg_sync_check(&mut self.sync_state as *mut ogg_sync_state) } == 0 {
+					Err(PageWriteError::NeedMoreData)
+				 } else {
+					Err(PageWriteError::InternalError)
+				 },
 				 1 => Ok(()),
 				 unexpected => panic!("ogg_sync_pageout should only return -1, 0, or 1, but returned {}", unexpected)
 			}
 		}
 
+		/// Take the next complete [Page] out of the `SyncState`.
+		fn next_page(&mut self) -> Result<Page, PageWriteError> {
+			let mut page: MaybeUninit<ogg_page> = MaybeUninit::uninit();
+			self.page_out(page.as_mut_ptr())?;
+
+			unsafe {
+				match Page::try_from(page.assume_init()) {
+					Err(error) => Err(PageWriteError::InvalidPage(error)),
+					// The page is copied out of the buffer of the sync
+					// state, which is reused on the next write
+					Ok(page) => Ok(page)
+				}
+			}
+		}
+
+		/// Return an iterator over every [Page] that can be completed
+		/// from the bytes written so far.
+		/// 
+		/// Skipped bytes are returned as [PageWriteError::OutOfSync]
+		/// without ending the iterator. It ends when more bytes are
+		/// needed to complete a page.
+		pub fn pages(&mut self) -> SyncPages<'_> {
+			SyncPages { sync_state: self, done: false }
+		}
+
 		/// Write bytes to the `SyncState` and return all [Pages](Page),
 		/// if any, that were completed from the input bytes.
+		/// 
+		/// Bytes that don't belong to any page are skipped.
 		pub fn submit_bytes(&mut self, bytes: &[u8]) -> Result<Option<Vec<Page>>, PageWriteError> {
 			self.write(bytes);
-			let mut page: MaybeUninit<ogg_page> = MaybeUninit::uninit();
 			let mut collected = vec![];
 
-			if self.page_out(page.as_mut_ptr()).is_err() { return Ok(None) }
-			let mut page = unsafe { page.assume_init() };
-			collected.push(
-				unsafe {
-					match Page::try_from(page) {
-						Err(_) => return Err(PageWriteError::InvalidPage),
-						Ok(page) => page.clone()
-					}
+			for page in self.pages() {
+				match page {
+					Err(PageWriteError::OutOfSync) => continue,
+					Err(error) => return Err(error),
+					Ok(page) => collected.push(page)
 				}
-			);
-
-			loop {
-				if self.page_out(&mut page).is_err() { break }
-				collected.push(
-					unsafe {
-						match Page::try_from(page) {
-							Err(_) => return Err(PageWriteError::InvalidPage),
-							Ok(page) => page.clone()
-						}
-					}
-				);
 			}
-			Ok(Some(collected))
+
+			if collected.is_empty() { Ok(None) } else { Ok(Some(collected)) }
 		}
 
-		/// Synchronizes to the next Page.
-		pub fn page_seek(&mut self, _: &mut Page) {
-			todo!()
+		/// Synchronizes to the next [Page].
+		/// 
+		/// Unlike [pages](SyncState::pages), this reports exactly how
+		/// many bytes were skipped while looking for a page, which
+		/// includes pages with a bad checksum.
+		pub fn page_seek(&mut self) -> Result<PageSeek, PageWriteError> {
+			let mut page: MaybeUninit<ogg_page> = MaybeUninit::uninit();
+			let code = unsafe {
+				ogg_sync_pageseek(&mut self.sync_state as *mut ogg_sync_state, page.as_mut_ptr())
+			};
+
+			match code {
+				0 => if unsafe { ogg_sync_check(&mut self.sync_state as *mut ogg_sync_state) } == 0 {
+					Err(PageWriteError::NeedMoreData)
+				} else {
+					Err(PageWriteError::InternalError)
+				},
+				skipped if skipped < 0 => Ok(PageSeek::Skipped(skipped.unsigned_abs() as usize)),
+				_ => unsafe {
+					match Page::try_from(page.assume_init()) {
+						Err(error) => Err(PageWriteError::InvalidPage(error)),
+						Ok(page) => Ok(PageSeek::Page(page))
+					}
+				}
+			}
 		}
 }
 
+// The sync state only points to memory that it owns.
+unsafe impl Send for SyncState {}
+
 impl Drop for SyncState {
 	fn drop(&mut self) {
 		let code;
@@ -170,20 +229,69 @@ impl Drop for SyncState {
 	}
 }
 
+/// The result of [SyncState::page_seek].
+#[derive(Clone)]
+pub enum PageSeek {
+	/// A complete page was found.
+	Page (Page),
+	/// This many bytes were skipped without finding a page.
+	Skipped (usize)
+}
+
+/// An iterator over the [Pages](Page) in a [SyncState].
+/// 
+/// See [SyncState::pages].
+pub struct SyncPages<'a> {
+	sync_state: &'a mut SyncState,
+	done: bool
+}
+
+impl Iterator for SyncPages<'_> {
+	type Item = Result<Page, PageWriteError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done { return None }
+
+		match self.sync_state.next_page() {
+			Err(PageWriteError::NeedMoreData) => { self.done = true; None },
+			Err(PageWriteError::InternalError) => { self.done = true; Some(Err(PageWriteError::InternalError)) },
+			result => Some(result)
+		}
+	}
+}
+
 /// An error that can happen while writing a page.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PageWriteError {
+	/// Sync was lost or not captured yet, and bytes were skipped.
 	OutOfSync,
+	/// There is not enough data to complete a page.
+	/// 
+	/// This is not really an error, submit more bytes and
+	/// try again.
+	NeedMoreData,
+	/// An internal error occurred in Ogg.
 	InternalError,
-	InvalidPage
+	/// Ogg returned a page that could not be read.
+	InvalidPage (InvalidPage)
 }
 
 impl std::fmt::Display for PageWriteError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
 			Self::OutOfSync => write!(f, "stream has not captured sync, bytes were skipped"),
-			Self::InternalError => write!(f, "not enough data has been submitted to complete a page or an internal error occurred"),
-			Self::InvalidPage => write!(f, "ogg returned an invalid page")
+			Self::NeedMoreData => write!(f, "not enough data has been submitted to complete a page"),
+			Self::InternalError => write!(f, "an internal error occurred in the sync state"),
+			Self::InvalidPage(_) => write!(f, "ogg returned an invalid page")
 		}
     }
 }
+
+impl std::error::Error for PageWriteError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::InvalidPage(error) => Some(error),
+			_ => None
+		}
+	}
+}
@@ -11,9 +11,10 @@ fn init_sync_state() {
 }
 
 #[test]
+#[allow(clippy::collapsible_else_if, clippy::unnecessary_unwrap, clippy::single_match)]
 fn sync_ogg_file() {
     let mut sync_state = match SyncState::new() {
-		Err(()) => panic!("initializing sync state failed!"),
+		Err(error) => panic!("initializing sync state failed: {}", error),
 		Ok(sync_state) => sync_state
 	};
 
@@ -58,7 +59,7 @@ fn sync_ogg_file() {
 	println!("found {} pages", pages.len());
 
 	let mut stream = match Stream::new(first_stream.unwrap()) {
-		Err(()) => panic!("initializing stream with serial {} returned error", first_stream.unwrap()),
+		Err(error) => panic!("initializing stream with serial {} returned error: {}", first_stream.unwrap(), error),
 		Ok(stream) => stream
 	};
 
@@ -76,16 +77,1561 @@ fn sync_ogg_file() {
 		}
 	}
 
-	let mut packets: Vec<Packet> = vec![];
+	let packets = match stream.packets().collect::<Result<Vec<Packet>, PacketOutError>>() {
+		Err(packet_out_error) => panic!("stream.packets returned an error: {}", packet_out_error),
+		Ok(packets) => packets
+	};
+	println!("found {} packets", packets.len())
+}
+
+#[test]
+fn packet_ref_to_packet() {
+	let mut sync_state = SyncState::new().expect("SyncState should initialize");
+	let mut pages = sync_state.submit_bytes(include_bytes!("../sine.ogg"))
+		.expect("sync state should not return an error")
+		.expect("sync state should return pages");
+	let mut stream = Stream::new(pages[0].stream_serial()).expect("Stream should initialize");
+	stream.page_in(&mut pages[0]).expect("first page should be accepted");
+
+	let peeked = stream.packet_peek().expect("stream should have a packet").to_packet();
+	let mut packet = stream.packet_out().expect("stream should have a packet").to_packet();
+	assert_eq!(peeked.data(), packet.data());
+	assert!(packet.begins_logical_stream());
+	assert!(packet.data().starts_with(b"OpusHead"));
+
+	// Owned packets can be changed freely
+	packet.data_mut()[0] = b'o';
+	assert!(packet.data().starts_with(b"opusHead"));
+}
+
+/// Encode `packets` into a single logical stream and return the
+/// bytes of every page.
+fn encode_packets(serial: i32, packets: &[Packet]) -> Vec<u8> {
+	let mut stream = Stream::new(serial).expect("Stream should initialize");
+	let mut bytes = vec![];
+
+	for packet in packets {
+		stream.packet_in(packet).expect("packet should be accepted");
+		for page in stream.pages() {
+			let page = page.expect("stream should return pages");
+			bytes.extend_from_slice(page.header());
+			bytes.extend_from_slice(page.data());
+		}
+	}
+	while let Ok(page) = stream.page_flush() {
+		bytes.extend_from_slice(page.header());
+		bytes.extend_from_slice(page.data());
+	}
+
+	bytes
+}
+
+/// Decode every packet of a single logical stream from `bytes`.
+fn decode_packets(bytes: &[u8]) -> Vec<Packet> {
+	let mut sync_state = SyncState::new().expect("SyncState should initialize");
+	let mut pages = sync_state.submit_bytes(bytes)
+		.expect("sync state should not return an error")
+		.expect("sync state should return pages");
+	let mut stream = Stream::new(pages[0].stream_serial()).expect("Stream should initialize");
+	let mut packets = vec![];
+
+	for page in &mut pages {
+		stream.page_in(page).expect("page should be accepted");
+		for packet in stream.packets() {
+			packets.push(packet.expect("stream should return packets"))
+		}
+	}
+
+	packets
+}
+
+#[test]
+fn encode_packets_round_trip() {
+	let mut packets = vec![];
+	for index in 0..20u8 {
+		// Data is set after construction, this has to be what
+		// ends up in the stream
+		let mut packet = Packet::new();
+		packet.set_data(vec![index; 100 * index as usize]);
+		packet.set_absgp(index as u64 * 960);
+		packet.set_ends_logical_stream(index == 19);
+		packets.push(packet)
+	}
+	packets[3].data_mut()[0] = 0xAA;
+
+	let decoded = decode_packets(&encode_packets(42, &packets));
+
+	assert_eq!(decoded.len(), packets.len());
+	for (index, (packet, decoded)) in packets.iter().zip(&decoded).enumerate() {
+		assert_eq!(packet.data(), decoded.data(), "packet {} data differs", index);
+		assert_eq!(decoded.index(), index as u32);
+	}
+	assert!(decoded[0].begins_logical_stream());
+	assert!(decoded[19].ends_logical_stream());
+	assert_eq!(decoded[19].absgp(), 19 * 960);
+}
+
+#[test]
+fn packet_in_vectored() {
+	let header = [1u8; 10];
+	let payload = [2u8; 600];
+	let padding = [0u8; 3];
+
+	let mut stream = Stream::new(7).expect("Stream should initialize");
+	// A header packet first, which ogg puts alone on the first page
+	stream.packet_in(&Packet::with_data(b"header".to_vec())).expect("packet should be accepted");
+	stream.packet_in_vectored(
+		&[std::io::IoSlice::new(&header), std::io::IoSlice::new(&payload), std::io::IoSlice::new(&padding)],
+		1234,
+		true
+	).expect("packet should be accepted");
+
+	let mut bytes = vec![];
+	while let Ok(page) = stream.page_flush() {
+		bytes.extend_from_slice(page.header());
+		bytes.extend_from_slice(page.data());
+	}
+
+	let decoded = decode_packets(&bytes);
+	assert_eq!(decoded.len(), 2);
+	assert_eq!(decoded[1].data(), [&header[..], &payload[..], &padding[..]].concat());
+	assert_eq!(decoded[1].absgp(), 1234);
+	assert!(decoded[1].ends_logical_stream());
+}
+
+#[test]
+fn error_conversions() {
+	use std::error::Error as _;
+
+	let error = Error::from(PageWriteError::InvalidPage(InvalidPage::InvalidHeader(InvalidPageHeader::TooShort)));
+	let source = error.source().expect("error should have a source");
+	assert_eq!(source.to_string(), InvalidPage::InvalidHeader(InvalidPageHeader::TooShort).to_string());
+	let source = source.source().expect("error should have a source");
+	assert_eq!(source.to_string(), InvalidPageHeader::TooShort.to_string());
+
+	let need_more_data = Error::from(PacketOutError::NeedMoreData);
+	assert!(need_more_data.needs_more_data());
+	assert_eq!(std::io::Error::from(need_more_data).kind(), std::io::ErrorKind::UnexpectedEof);
+
+	let out_of_sync = std::io::Error::from(Error::from(PacketOutError::OutOfSync));
+	assert_eq!(out_of_sync.kind(), std::io::ErrorKind::InvalidData);
+	assert!(!Error::from(PacketOutError::InternalError).needs_more_data());
+
+	let mut stream = Stream::new(1).expect("Stream should initialize");
+	assert_eq!(stream.page_flush().err(), Some(PageOutError::NeedMoreData));
+	assert_eq!(stream.packet_out().err(), Some(PacketOutError::NoPages));
+}
+
+#[test]
+fn iterators_report_holes() {
+	let bytes = include_bytes!("../sine.ogg");
+	let mut sync_state = SyncState::new().expect("SyncState should initialize");
+	let mut pages = sync_state.submit_bytes(bytes)
+		.expect("sync state should not return an error")
+		.expect("sync state should return pages");
+	let page_count = pages.len();
+
+	// Garbage between pages is reported, but reading continues
+	let mut sync_state = SyncState::new().expect("SyncState should initialize");
+	let first_page_len = pages[0].header().len() + pages[0].data().len();
+	sync_state.write(&bytes[..first_page_len]);
+	sync_state.write(b"garbage");
+	sync_state.write(&bytes[first_page_len..]);
+	let results: Vec<_> = sync_state.pages().collect();
+	assert_eq!(results.iter().filter(|page| page.is_ok()).count(), page_count);
+	assert_eq!(results.iter().filter(|page| page.is_err()).count(), 1);
+	assert_eq!(results[1].as_ref().err(), Some(&PageWriteError::OutOfSync));
+
+	// A missing page is reported, but reading continues
+	let mut stream = Stream::new(pages[0].stream_serial()).expect("Stream should initialize");
+	for (index, page) in pages.iter_mut().enumerate() {
+		if index != 3 {
+			stream.page_in(page).expect("page should be accepted")
+		}
+	}
+	let results: Vec<_> = stream.packets().collect();
+	assert_eq!(results.iter().filter(|packet| packet.is_err()).count(), 1);
+	assert!(results.iter().filter(|packet| packet.is_ok()).count() > 2);
+	assert!(results.last().unwrap().is_ok());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_page_and_packet_readers() {
+	use futures_util::StreamExt;
+	use tokio::io::AsyncWriteExt;
+
+	let bytes = include_bytes!("../sine.ogg");
+	let expected_pages = SyncState::new().unwrap().submit_bytes(bytes).unwrap().unwrap();
+	let expected_packets = decode_packets(bytes);
+
+	// Pages
+	let (mut writer, reader) = tokio::io::duplex(64);
+	let write = tokio::spawn(async move {
+		for chunk in bytes.chunks(100) {
+			writer.write_all(chunk).await.unwrap()
+		}
+	});
+	let pages: Vec<Page> = AsyncPageReader::new(reader).unwrap()
+		.map(|page| page.expect("reader should not return an error"))
+		.collect().await;
+	write.await.unwrap();
+
+	assert_eq!(pages.len(), expected_pages.len());
+	for (page, expected) in pages.iter().zip(&expected_pages) {
+		assert_eq!(page.header(), expected.header());
+		assert_eq!(page.data(), expected.data());
+	}
+
+	// Packets
+	let (mut writer, reader) = tokio::io::duplex(64);
+	let write = tokio::spawn(async move {
+		writer.write_all(bytes).await.unwrap()
+	});
+	let packets: Vec<(i32, Packet)> = AsyncPacketReader::new(reader).unwrap()
+		.map(|packet| packet.expect("reader should not return an error"))
+		.collect().await;
+	write.await.unwrap();
+
+	assert_eq!(packets.len(), expected_packets.len());
+	for ((serial, packet), expected) in packets.iter().zip(&expected_packets) {
+		assert_eq!(*serial, expected_pages[0].stream_serial());
+		assert_eq!(packet, expected);
+	}
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_page_writer() {
+	use futures_util::StreamExt;
+
+	let packets: Vec<Packet> = (0..50u32).map(|index| {
+		let mut packet = Packet::with_data(vec![index as u8; 300]);
+		packet.set_absgp(index as u64 * 960);
+		packet.set_ends_logical_stream(index == 49);
+		packet
+	}).collect();
+
+	let (writer, reader) = tokio::io::duplex(256);
+	let to_write = packets.clone();
+	let write = tokio::spawn(async move {
+		let mut stream = Stream::new(99).unwrap();
+		let mut writer = AsyncPageWriter::new(writer);
+		for packet in &to_write {
+			stream.packet_in(packet).unwrap();
+			writer.write_pages(&mut stream).await.unwrap()
+		}
+		writer.flush_stream(&mut stream).await.unwrap();
+		writer.flush().await.unwrap()
+	});
+
+	let read: Vec<Packet> = AsyncPacketReader::new(reader).unwrap()
+		.map(|packet| packet.expect("reader should not return an error").1)
+		.collect().await;
+	write.await.unwrap();
+
+	assert_eq!(read.len(), packets.len());
+	for (read, packet) in read.iter().zip(&packets) {
+		assert_eq!(read.data(), packet.data())
+	}
+	assert!(read[49].ends_logical_stream());
+}
+
+#[cfg(all(feature = "codec", unix))]
+#[tokio::test]
+async fn page_codec_over_socket() {
+	use futures_util::{ SinkExt, StreamExt };
+	use tokio_util::codec::{ Framed, FramedRead };
+	use tokio::io::AsyncWriteExt;
+
+	let bytes = include_bytes!("../sine.ogg");
+	let expected_pages = SyncState::new().unwrap().submit_bytes(bytes).unwrap().unwrap();
+
+	// Raw bytes in, pages out
+	let (mut sender, receiver) = tokio::net::UnixStream::pair().unwrap();
+	let write = tokio::spawn(async move {
+		sender.write_all(b"garbage before the first page").await.unwrap();
+		for chunk in bytes.chunks(1000) {
+			sender.write_all(chunk).await.unwrap()
+		}
+	});
+	let pages: Vec<Page> = FramedRead::new(receiver, PageCodec::new().unwrap())
+		.map(|page| page.expect("codec should not return an error"))
+		.collect().await;
+	write.await.unwrap();
+
+	assert_eq!(pages.len(), expected_pages.len());
+
+	// Pages in, pages out
+	let (sender, receiver) = tokio::net::UnixStream::pair().unwrap();
+	let to_send = expected_pages.clone();
+	let write = tokio::spawn(async move {
+		let mut framed = Framed::new(sender, PageCodec::new().unwrap());
+		for page in to_send {
+			framed.send(page).await.unwrap()
+		}
+	});
+	let pages: Vec<Page> = FramedRead::new(receiver, PageCodec::new().unwrap())
+		.map(|page| page.expect("codec should not return an error"))
+		.collect().await;
+	write.await.unwrap();
+
+	assert_eq!(pages.len(), expected_pages.len());
+	for (page, expected) in pages.iter().zip(&expected_pages) {
+		assert_eq!(page.header(), expected.header());
+		assert_eq!(page.data(), expected.data());
+	}
+}
+
+/// Return the offset and size of every page in `bytes`.
+fn page_spans(bytes: &[u8]) -> Vec<(usize, usize)> {
+	PageReader::new(bytes).unwrap()
+		.map(|page| page.unwrap())
+		.map(|(offset, page)| (offset as usize, page.size()))
+		.collect()
+}
+
+#[test]
+fn damage_report_intact() {
+	let bytes = include_bytes!("../sine.ogg");
+	let report = damage_report(&bytes[..]).unwrap();
+
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+	assert_eq!(report.bytes, bytes.len() as u64);
+	assert_eq!(report.pages, page_spans(bytes).len() as u64);
+	assert_eq!(report.packets, decode_packets(bytes).len() as u64);
+
+	// A chained file whose links share a serial number
+	let chained = [&bytes[..], &bytes[..]].concat();
+	assert!(damage_report(&chained[..]).unwrap().is_intact());
+}
+
+#[test]
+fn damage_report_damaged() {
+	let bytes = include_bytes!("../sine.ogg");
+	let spans = page_spans(bytes);
+	let serial = SyncState::new().unwrap().submit_bytes(bytes).unwrap().unwrap()[0].stream_serial();
+
+	let mut damaged = vec![];
+	// Garbage between pages 2 and 3
+	damaged.extend_from_slice(&bytes[..spans[3].0]);
+	let garbage_offset = damaged.len() as u64;
+	damaged.extend_from_slice(b"not a page");
+	// A bad checksum on page 5
+	damaged.extend_from_slice(&bytes[spans[3].0..spans[5].0]);
+	let bad_page_offset = damaged.len() as u64;
+	damaged.extend_from_slice(&bytes[spans[5].0..]);
+	damaged[bad_page_offset as usize + spans[5].1 - 10] ^= 0xFF;
+	// Cut off in the middle of the last page
+	let (last_offset, last_size) = spans[spans.len() - 1];
+	let truncated_offset = (last_offset + "not a page".len()) as u64;
+	damaged.truncate(truncated_offset as usize + last_size / 2);
+
+	let report = damage_report(&damaged[..]).unwrap();
+
+	assert_eq!(report.bytes, damaged.len() as u64);
+	assert_eq!(report.pages, spans.len() as u64 - 2);
+	assert_eq!(report.damage, vec![
+		Damage::SkippedBytes { offset: garbage_offset, length: 10 },
+		Damage::BadChecksum { offset: bad_page_offset, length: spans[5].1 as u64, serial, index: 5 },
+		Damage::SkippedBytes { offset: bad_page_offset, length: spans[5].1 as u64 },
+		Damage::SequenceGap { offset: bad_page_offset + spans[5].1 as u64, serial, expected: 5, found: 6 },
+		Damage::PacketsLost { offset: bad_page_offset + spans[5].1 as u64, serial },
+		Damage::Truncated { offset: truncated_offset, length: last_size as u64 / 2 }
+	]);
+}
+
+#[test]
+fn repair_intact_file() {
+	let bytes = include_bytes!("../sine.ogg");
+	let mut repaired = vec![];
+	let summary = repair(&bytes[..], &mut repaired).unwrap();
+
+	assert_eq!(repaired, bytes);
+	assert_eq!(summary.pages_read, page_spans(bytes).len() as u64);
+	assert_eq!(summary.pages_written, summary.pages_read);
+	assert_eq!(summary, RepairSummary {
+		pages_read: summary.pages_read,
+		pages_written: summary.pages_written,
+		..RepairSummary::default()
+	});
+}
+
+#[test]
+fn repair_damaged_file() {
+	let bytes = include_bytes!("../sine.ogg");
+	let spans = page_spans(bytes);
+
+	let mut damaged = vec![];
+	// A bad checksum on page 3
+	damaged.extend_from_slice(&bytes[..spans[3].0]);
+	let bad_page_offset = damaged.len();
+	damaged.extend_from_slice(&bytes[spans[3].0..spans[5].0]);
+	damaged[bad_page_offset + spans[3].1 - 10] ^= 0xFF;
+	// Page 5 is lost
+	damaged.extend_from_slice(&bytes[spans[6].0..]);
+	// Cut off in the middle of the last page
+	let last_size = spans[spans.len() - 1].1;
+	damaged.truncate(damaged.len() - last_size / 2);
+
+	let mut repaired = vec![];
+	let summary = repair(&damaged[..], &mut repaired).unwrap();
+
+	assert_eq!(summary.pages_read, spans.len() as u64 - 2);
+	assert_eq!(summary.pages_written, summary.pages_read);
+	assert_eq!(summary.checksums_fixed, 1);
+	assert_eq!(summary.pages_renumbered, 1);
+	assert_eq!(summary.end_of_stream_added, 1);
+	assert!(!summary.damage.is_empty());
+
+	let report = damage_report(&repaired[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+	assert_eq!(report.pages, summary.pages_written);
+
+	let last = PageReader::new(&repaired[..]).unwrap().last().unwrap().unwrap().1;
+	assert!(last.ends_logical_stream());
+	assert_eq!(last.index(), spans.len() as u32 - 3);
+}
+
+#[test]
+fn salvage_truncated_file() {
+	let bytes = include_bytes!("../sine.ogg");
+	let spans = page_spans(bytes);
+	let (last_offset, last_size) = spans[spans.len() - 1];
+
+	let mut salvaged = vec![];
+	let summary = salvage(&bytes[..last_offset + last_size / 2], &mut salvaged).unwrap();
+
+	assert_eq!(summary.pages_written, spans.len() as u64 - 1);
+	assert_eq!(summary.end_of_stream_added, 1);
+	assert_eq!(summary.partial_packets_dropped, 0);
+	// Only the new last page differs
+	let (before_offset, before_size) = spans[spans.len() - 2];
+	assert_eq!(salvaged.len(), last_offset);
+	assert_eq!(salvaged[..before_offset], bytes[..before_offset]);
+
+	let report = damage_report(&salvaged[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+	let last = PageReader::new(&salvaged[before_offset..before_offset + before_size]).unwrap()
+		.next().unwrap().unwrap().1;
+	assert!(last.ends_logical_stream());
+	assert!(last.verify_crc_checksum());
+	assert_eq!(last.absgp(), decode_packets(&salvaged).last().unwrap().absgp());
+}
+
+/// Replace the granule position of the last page in `bytes`
+/// with `-1`.
+fn drop_last_granule(bytes: &[u8]) -> Vec<u8> {
+	let spans = page_spans(bytes);
+	let (last_offset, _) = spans[spans.len() - 1];
+	let mut last = PageReader::new(&bytes[last_offset..]).unwrap().next().unwrap().unwrap().1;
+	last.set_absgp(u64::MAX);
+	last.set_crc_checksum();
+	let mut damaged = bytes[..last_offset].to_vec();
+	damaged.extend_from_slice(last.header());
+	damaged.extend_from_slice(last.data());
+	damaged
+}
+
+#[test]
+fn repair_last_page_without_granule() {
+	// Counted forward from the page before
+	let bytes = opus_stream(3, &[], 400);
+	let mut repaired = vec![];
+	let summary = repair(&drop_last_granule(&bytes)[..], &mut repaired).unwrap();
+	assert_eq!((summary.granules_fixed, summary.granules_unknown), (1, 0));
+	assert_eq!(repaired, bytes);
+
+	// Packets of an unknown codec
+	let packets: Vec<Packet> = (0..400u64)
+		.map(|index| {
+			let mut packet = Packet::with_data(vec![index as u8; 100]);
+			packet.set_absgp(index * 960);
+			packet
+		})
+		.collect();
+	let mut repaired = vec![];
+	let summary = repair(&drop_last_granule(&encode_packets(4, &packets))[..], &mut repaired).unwrap();
+	assert_eq!((summary.granules_fixed, summary.granules_unknown), (1, 1));
+	let spans = page_spans(&repaired);
+	let before = PageReader::new(&repaired[spans[spans.len() - 2].0..]).unwrap().next().unwrap().unwrap().1;
+	assert_eq!(decode_packets(&repaired).last().unwrap().absgp(), before.absgp());
+}
+
+#[test]
+fn drop_unfinished_packet_from_first_page() {
+	// One packet that spans every page and is cut off
+	let mut packet = Packet::with_data(vec![1; 300000]);
+	packet.set_absgp(960);
+	let bytes = encode_packets(8, &[packet]);
+	let spans = page_spans(&bytes);
+	let truncated = &bytes[..spans[3].0 + spans[3].1 / 2];
+
+	// Sequence numbers from 0, and wrapping around past 0
+	for first in [0, u32::MAX - 1] {
+		let mut renumbered = vec![];
+		for (index, (_, mut page)) in PageReader::new(truncated).unwrap().map(Result::unwrap).enumerate() {
+			page.set_index(first.wrapping_add(index as u32));
+			page.set_crc_checksum();
+			renumbered.extend_from_slice(page.header());
+			renumbered.extend_from_slice(page.data());
+		}
+
+		let mut repaired = vec![];
+		let summary = repair(&renumbered[..], &mut repaired).unwrap();
+		assert_eq!(summary.partial_packets_dropped, 1);
+		assert!(repaired.is_empty());
+	}
+}
+
+#[test]
+fn salvage_drops_unfinished_packet() {
+	let mut packets = vec![];
+	for index in 0..8u8 {
+		// Packet 6 spans several pages
+		let size = if index == 6 { 100000 } else { 1000 };
+		let mut packet = Packet::with_data(vec![index; size]);
+		packet.set_absgp(index as u64 * 960);
+		packets.push(packet)
+	}
+	let bytes = encode_packets(7, &packets);
+	let spans = page_spans(&bytes);
+	// Cut off after the first page with part of packet 6
+	let cut = spans.iter()
+		.position(|(offset, size)| {
+			let page = &PageReader::new(&bytes[*offset..offset + size]).unwrap().next().unwrap().unwrap().1;
+			page.lacing_values().last() == Some(&255)
+		})
+		.unwrap();
+	let (cut_offset, cut_size) = spans[cut + 1];
+
+	let mut salvaged = vec![];
+	let summary = salvage(&bytes[..cut_offset + cut_size / 2], &mut salvaged).unwrap();
+
+	assert_eq!(summary.partial_packets_dropped, 1);
+	assert_eq!(summary.end_of_stream_added, 1);
+	let decoded = decode_packets(&salvaged);
+	assert_eq!(decoded.len(), 6);
+	for (packet, decoded) in packets.iter().zip(&decoded) {
+		assert_eq!(packet.data(), decoded.data())
+	}
+	assert!(decoded[5].ends_logical_stream());
+	assert_eq!(decoded[5].absgp(), 5 * 960);
+	assert!(damage_report(&salvaged[..]).unwrap().is_intact());
+}
+
+#[test]
+fn append_to_stream() {
+	let packets: Vec<Packet> = (0..20u8)
+		.map(|index| {
+			let mut packet = Packet::with_data(vec![index; 500]);
+			packet.set_absgp(index as u64 * 960);
+			packet.set_ends_logical_stream(index == 9 || index == 19);
+			packet
+		})
+		.collect();
+
+	let mut writer = PacketWriter::new(std::io::Cursor::new(vec![]), 99).unwrap();
+	for packet in &packets[..10] {
+		writer.write_packet(packet).unwrap()
+	}
+	let mut file = writer.into_inner().unwrap();
+
+	assert!(matches!(append(&mut file, 98), Err(Error::Append(AppendError::StreamNotFound(98)))));
+	// Another logical stream after the end of this one
+	let mut chained = std::io::Cursor::new([file.get_ref().clone(), include_bytes!("../sine.ogg").to_vec()].concat());
+	assert!(matches!(append(&mut chained, 99), Err(Error::Append(AppendError::StreamNotAtEnd(99)))));
+	// Garbage after the last page
+	let mut trailing = std::io::Cursor::new([file.get_ref().clone(), b"garbage".to_vec()].concat());
+	assert!(matches!(append(&mut trailing, 99), Err(Error::Append(AppendError::TrailingBytes(7)))));
+	assert_eq!(trailing.get_ref()[..file.get_ref().len()], file.get_ref()[..]);
+
+	let mut writer = append(&mut file, 99).unwrap();
+	for packet in &packets[10..] {
+		writer.write_packet(packet).unwrap()
+	}
+	writer.into_inner().unwrap();
+	let bytes = file.into_inner();
+
+	let report = damage_report(&bytes[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+	let pages: Vec<Page> = PageReader::new(&bytes[..]).unwrap().map(|page| page.unwrap().1).collect();
+	assert_eq!(pages.iter().filter(|page| page.begins_logical_stream()).count(), 1);
+	assert_eq!(pages.iter().filter(|page| page.ends_logical_stream()).count(), 1);
+	assert!(pages.last().unwrap().ends_logical_stream());
+
+	let decoded = decode_packets(&bytes);
+	assert_eq!(decoded.len(), packets.len());
+	for (packet, decoded) in packets.iter().zip(&decoded) {
+		assert_eq!(packet.data(), decoded.data())
+	}
+	assert_eq!(decoded[19].absgp(), 19 * 960);
+}
+
+#[test]
+fn concat_with_serial_collision() {
+	let bytes = include_bytes!("../sine.ogg");
+	let serial = PageReader::new(&bytes[..]).unwrap().next().unwrap().unwrap().1.stream_serial();
+
+	let mut joined = vec![];
+	let reserialed = concat([&bytes[..], &bytes[..], &bytes[..]], &mut joined).unwrap();
+
+	assert_eq!(reserialed, vec![
+		Reserial { input: 1, from: serial, to: serial + 1 },
+		Reserial { input: 2, from: serial, to: serial + 2 }
+	]);
+	assert_eq!(joined.len(), bytes.len() * 3);
+	let report = damage_report(&joined[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+
+	let pages: Vec<Page> = PageReader::new(&joined[..]).unwrap().map(|page| page.unwrap().1).collect();
+	let starts: Vec<i32> = pages.iter()
+		.filter(|page| page.begins_logical_stream())
+		.map(|page| page.stream_serial())
+		.collect();
+	assert_eq!(starts, vec![serial, serial + 1, serial + 2]);
+	assert_eq!(decode_packets(&joined[bytes.len()..bytes.len() * 2]), decode_packets(bytes));
+}
+
+#[test]
+fn concat_requires_end_of_stream() {
+	let bytes = include_bytes!("../sine.ogg");
+	let spans = page_spans(bytes);
+	let unfinished = &bytes[..spans[spans.len() - 1].0];
+
+	let result = concat([unfinished, &bytes[..]], std::io::sink());
+	assert!(matches!(result, Err(Error::Concat(ConcatError::MissingEndOfStream { input: 0, .. }))));
+}
+
+/// Return an Opus comment header with the comments `fields`.
+fn opus_tags(fields: &[&str]) -> Vec<u8> {
+	let mut tags = b"OpusTags".to_vec();
+	tags.extend_from_slice(&8u32.to_le_bytes());
+	tags.extend_from_slice(b"ogg_xiph");
+	tags.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+	for field in fields {
+		tags.extend_from_slice(&(field.len() as u32).to_le_bytes());
+		tags.extend_from_slice(field.as_bytes());
+	}
+	tags
+}
+
+/// Encode an Opus stream with the comments `fields` and
+/// `frames` packets of 20 ms.
+fn opus_stream(serial: i32, fields: &[&str], frames: u64) -> Vec<u8> {
+	let mut head = b"OpusHead\x01\x01".to_vec();
+	head.extend_from_slice(&312u16.to_le_bytes());
+	head.extend_from_slice(&48000u32.to_le_bytes());
+	head.extend_from_slice(&[0, 0, 0]);
+
+	let mut packets = vec![Packet::with_data(head), Packet::with_data(opus_tags(fields))];
+	for frame in 0..frames {
+		// One CELT frame of 20 ms
+		let mut packet = Packet::with_data(vec![0xF8, frame as u8, 0, 0]);
+		packet.set_absgp((frame + 1) * 960);
+		packet.set_ends_logical_stream(frame + 1 == frames);
+		packets.push(packet)
+	}
+
+	encode_packets(serial, &packets)
+}
+
+#[test]
+fn parse_comments() {
+	let tags = opus_tags(&["ARTIST=Someone", "title=A Song", "EMPTY"]);
+	let comments = Comments::parse(Codec::Opus, &tags).unwrap();
+
+	assert_eq!(comments.vendor, "ogg_xiph");
+	assert_eq!(comments.get("artist"), Some("Someone"));
+	assert_eq!(comments.get("TITLE"), Some("A Song"));
+	assert_eq!(comments.get("EMPTY"), Some(""));
+	assert_eq!(Comments::parse(Codec::Vorbis, &tags), None);
+	assert_eq!(Codec::identify(b"OpusHead\x01"), Codec::Opus);
+	assert_eq!(Codec::identify(b"\x01vorbis"), Codec::Vorbis);
+}
+
+/// A writer that can be looked into after giving it away.
+#[derive(Clone, Default)]
+struct SharedBuffer (std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.borrow_mut().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+}
+
+#[test]
+fn split_chained_file() {
+	let first = opus_stream(1, &["ARTIST=Some/one", "TITLE=First"], 200);
+	let second = opus_stream(2, &[], 100);
+	let mut chained = first.clone();
+	chained.extend_from_slice(&second);
+
+	let mut outputs = vec![];
+	let links = split_links(&chained[..], |link| {
+		let output = SharedBuffer::default();
+		outputs.push((link.file_name(), output.clone()));
+		Ok(output)
+	}).unwrap();
+	let outputs: Vec<(String, Vec<u8>)> = outputs.into_iter()
+		.map(|(name, output)| (name, output.0.take()))
+		.collect();
+
+	assert_eq!(links.len(), 2);
+	assert_eq!(links[1].offset, first.len() as u64);
+	assert_eq!(links[0].streams[0].codec, Codec::Opus);
+	assert_eq!(links[0].comment("title"), Some("First"));
+	assert_eq!(outputs[0].0, "01 - Some_one - First.opus");
+	assert_eq!(outputs[1].0, "02.opus");
+	assert_eq!(outputs[0].1, first);
+	assert_eq!(outputs[1].1, second);
+}
+
+/// Multiplex the physical streams `streams`, with every
+/// beginning of stream page first and the other pages taking
+/// turns.
+fn multiplex(streams: &[&[u8]]) -> Vec<u8> {
+	let mut pages: Vec<std::collections::VecDeque<Page>> = streams.iter()
+		.map(|bytes| PageReader::new(*bytes).unwrap().map(|page| page.unwrap().1).collect())
+		.collect();
+	let mut bytes = vec![];
+	let mut write = |page: Page| {
+		bytes.extend_from_slice(page.header());
+		bytes.extend_from_slice(page.data());
+	};
+
+	for stream in &mut pages {
+		write(stream.pop_front().unwrap())
+	}
+	while pages.iter().any(|stream| !stream.is_empty()) {
+		for stream in &mut pages {
+			if let Some(page) = stream.pop_front() { write(page) }
+		}
+	}
+
+	bytes
+}
+
+/// Encode a stream that looks like Vorbis, with `packets`
+/// packets after the headers.
+fn vorbis_like_stream(serial: i32, packets: u64) -> Vec<u8> {
+	let mut header = b"\x01vorbis".to_vec();
+	header.extend_from_slice(&0u32.to_le_bytes());
+	header.push(1);
+	header.extend_from_slice(&48000u32.to_le_bytes());
+	header.extend_from_slice(&[0; 12]);
+	header.extend_from_slice(&[0xB8, 1]);
+	let mut all = vec![
+		Packet::with_data(header),
+		Packet::with_data(b"\x03vorbis".to_vec()),
+		Packet::with_data(b"\x05vorbis".to_vec())
+	];
+	for index in 0..packets {
+		let mut packet = Packet::with_data(vec![index as u8; 300]);
+		packet.set_absgp((index + 1) * 1024);
+		packet.set_ends_logical_stream(index + 1 == packets);
+		all.push(packet)
+	}
+	encode_packets(serial, &all)
+}
+
+#[test]
+fn extract_and_drop_streams() {
+	let opus = opus_stream(1, &[], 300);
+	let vorbis = vorbis_like_stream(2, 100);
+	let multiplexed = multiplex(&[&opus, &vorbis]);
+
+	let mut extracted = vec![];
+	assert_eq!(extract(&multiplexed[..], &mut extracted, Selection::Serial(2)).unwrap(), vec![2]);
+	assert_eq!(extracted, vorbis);
+
+	let mut extracted = vec![];
+	assert_eq!(extract(&multiplexed[..], &mut extracted, Selection::Codec(Codec::Opus)).unwrap(), vec![1]);
+	assert_eq!(extracted, opus);
+
+	let mut kept = vec![];
+	assert_eq!(drop_streams(&multiplexed[..], &mut kept, &[Selection::Codec(Codec::Opus)]).unwrap(), vec![2]);
+	assert_eq!(kept, vorbis);
+
+	let result = extract(&multiplexed[..], std::io::sink(), Selection::Codec(Codec::Theora));
+	assert!(matches!(result, Err(Error::Extract(ExtractError::NotFound(_)))));
+}
+
+#[test]
+fn insert_stream_by_time() {
+	let opus = opus_stream(1, &[], 300);
+	let vorbis = vorbis_like_stream(1, 100);
+
+	let mut output = vec![];
+	let serial = insert_stream(std::io::Cursor::new(&opus), &vorbis[..], &mut output).unwrap();
+	assert_eq!(serial, 2);
+
+	let report = damage_report(&output[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+
+	let pages: Vec<Page> = PageReader::new(&output[..]).unwrap().map(|page| page.unwrap().1).collect();
+	assert!(pages[0].begins_logical_stream() && pages[0].stream_serial() == 1);
+	assert!(pages[1].begins_logical_stream() && pages[1].stream_serial() == 2);
+	// Data pages are in order of time
+	let timebases = [
+		Timebase::from_header(Codec::Opus, pages[0].data()).unwrap(),
+		Timebase::from_header(Codec::Vorbis, pages[1].data()).unwrap()
+	];
+	let times: Vec<f64> = pages.iter()
+		.filter(|page| page.absgp() != 0 && page.absgp() != u64::MAX)
+		.map(|page| timebases[page.stream_serial() as usize - 1].seconds(page.absgp()))
+		.collect();
+	assert!(times.windows(2).all(|pair| pair[0] <= pair[1]), "pages out of order: {:?}", times);
+
+	let mut extracted = vec![];
+	extract(&output[..], &mut extracted, Selection::Serial(1)).unwrap();
+	assert_eq!(extracted, opus);
+	let mut extracted = vec![];
+	extract(&output[..], &mut extracted, Selection::Serial(2)).unwrap();
+	assert_eq!(decode_packets(&extracted), decode_packets(&vorbis));
+}
+
+#[test]
+fn cut_opus_exactly() {
+	let opus = opus_stream(1, &["TITLE=Clip"], 300);
+	let mut output = vec![];
+	let summary = cut(&opus[..], &mut output, 1.0, Some(2.0)).unwrap();
+
+	// Packet 46 starts 80 ms before 1 s, packet 100 ends after 2 s
+	assert_eq!(summary, CutSummary { serial: 1, codec: Codec::Opus, start: 46 * 960, end: 312 + 96000, packets: 55 });
+	let report = damage_report(&output[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+
+	let packets = decode_packets(&output);
+	assert_eq!(packets.len(), 2 + 55);
+	let timebase = Timebase::from_header(Codec::Opus, packets[0].data()).unwrap();
+	assert_eq!(timebase.pre_skip, 312 + 48000 - 46 * 960);
+	assert_eq!(packets[1].data(), &opus_tags(&["TITLE=Clip"])[..]);
+	assert_eq!(packets[2].data(), &[0xF8, 46, 0, 0]);
+	let last = packets.last().unwrap();
+	assert!(last.ends_logical_stream());
+	assert_eq!(timebase.seconds(last.absgp()), 1.0);
+}
+
+#[test]
+fn cut_vorbis_by_page() {
+	let vorbis = vorbis_like_stream(2, 100);
+	let mut output = vec![];
+	let summary = cut(&vorbis[..], &mut output, 0.5, Some(1.0)).unwrap();
+
+	let report = damage_report(&output[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+	assert_eq!(summary.start, 24000);
+	assert_eq!(summary.end, 48000);
+
+	let input = decode_packets(&vorbis);
+	let packets = decode_packets(&output);
+	assert_eq!(packets.len(), 3 + summary.packets as usize);
+	// A run of data packets around the range
+	let first = input.iter().position(|packet| packet.data() == packets[3].data()).unwrap();
+	for (packet, original) in packets[3..].iter().zip(&input[first..]) {
+		assert_eq!(packet.data(), original.data())
+	}
+	// The first packet ends before the start, for pre-roll
+	assert!((first as u64 - 2) * 1024 <= 24000);
+	assert!((first as u64 - 3 + summary.packets) * 1024 >= 48000);
+	let last = packets.last().unwrap();
+	assert!(last.ends_logical_stream());
+	assert_eq!(last.absgp(), 48000 - summary.start);
+
+	// The pre-roll is on the first data page, which trims
+	// everything before the start
+	let mut pages = PageReader::new(&output[..]).unwrap();
+	let page = std::iter::from_fn(|| pages.next_page().unwrap())
+		.map(|(_, page)| page)
+		.find(|page| page.absgp() > 0)
+		.unwrap();
+	let end = page.absgp() + 24000;
+	assert!(end % 1024 == 0 && end <= 48000);
+	// Less than the packets after the first one decode to
+	assert!(page.absgp() < end - (first as u64 - 2) * 1024);
+}
+
+#[test]
+fn cut_vorbis_gives_every_page_a_granule() {
+	// Packets of one segment, so the first page of the cut holds
+	// more than a whole page of the input
+	let mut packets = vorbis_headers();
+	for index in 0..2000u64 {
+		let mut packet = Packet::with_data(vec![2]);
+		packet.set_absgp(index * 1024);
+		packet.set_ends_logical_stream(index == 1999);
+		packets.push(packet)
+	}
+	let vorbis = encode_packets(3, &packets);
+
+	let mut output = vec![];
+	let summary = cut(&vorbis[..], &mut output, 10.0, Some(20.0)).unwrap();
+	assert_eq!(summary.start, 480000);
+	let report = damage_report(&output[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+
+	let pages: Vec<Page> = PageReader::new(&output[..]).unwrap().map(|page| page.unwrap().1).collect();
+	assert!(pages[2].finished_packets() == 255);
+	for page in &pages {
+		assert!(page.finished_packets() == 0 || page.absgp() != u64::MAX)
+	}
+	assert_eq!(pages.last().unwrap().absgp(), 480000);
+}
+
+#[test]
+fn cut_unsupported_codec() {
+	let theora = encode_packets(3, &[Packet::with_data(b"\x80theora".to_vec())]);
+	let result = cut(&theora[..], std::io::sink(), 0.0, None);
+	assert!(matches!(result, Err(Error::Cut(CutError::Unsupported(Codec::Theora)))));
+}
+
+/// Repaginate a physical stream, check it's intact and carries
+/// the same packets, and return its pages.
+fn repaginated(bytes: &[u8], pacing: PagePacing) -> (RepaginateSummary, Vec<Page>) {
+	let mut output = vec![];
+	let summary = repaginate(bytes, &mut output, pacing).unwrap();
+	let report = damage_report(&output[..]).unwrap();
+	assert!(report.is_intact(), "unexpected damage: {:?}", report.damage);
+
+	let data = |packets: Vec<Packet>| packets.into_iter().map(|packet| packet.data().to_vec()).collect::<Vec<_>>();
+	assert_eq!(data(decode_packets(&output)), data(decode_packets(bytes)));
+	let pages: Vec<Page> = PageReader::new(&output[..]).unwrap().map(|page| page.unwrap().1).collect();
+	assert_eq!(summary.pages_written, pages.len() as u64);
+	// Headers are on pages of their own
+	assert_eq!(pages[0].finished_packets(), 1);
+	assert_eq!(pages.iter().take_while(|page| page.absgp() == 0).count(), 2);
+	(summary, pages)
+}
+
+#[test]
+fn repaginate_opus() {
+	let opus = opus_stream(1, &["TITLE=Pages"], 100);
+
+	let (summary, pages) = repaginated(&opus, PagePacing::OnePacketPerPage);
+	assert_eq!(summary.packets, 102);
+	assert_eq!(pages.len(), 102);
+	for (frame, page) in pages[2..].iter().enumerate() {
+		assert_eq!(page.absgp(), (frame as u64 + 1) * 960)
+	}
+	assert!(pages.last().unwrap().ends_logical_stream());
+
+	let (_, pages) = repaginated(&opus, PagePacing::MaxPageGranules(9600));
+	assert_eq!(pages.len(), 12);
+	for (index, page) in pages[2..].iter().enumerate() {
+		assert_eq!(page.absgp(), (index as u64 + 1) * 9600)
+	}
+
+	let (_, pages) = repaginated(&opus, PagePacing::MaxPageBytes(std::num::NonZeroUsize::new(40).unwrap()));
+	assert_eq!(pages.len(), 12);
+	assert!(pages[2..].iter().all(|page| page.finished_packets() == 10));
+}
+
+#[test]
+fn repaginate_vorbis_at_known_granules() {
+	let vorbis = vorbis_like_stream(2, 100);
+	let input: Vec<u64> = PageReader::new(&vorbis[..]).unwrap().map(|page| page.unwrap().1.absgp()).collect();
+
+	// Pages can only end where the input has a granule position
+	let (summary, pages) = repaginated(&vorbis, PagePacing::OnePacketPerPage);
+	assert_eq!(summary.pages_read, input.len() as u64);
+	for page in &pages {
+		assert!(input.contains(&page.absgp()))
+	}
+
+	let (_, pages) = repaginated(&vorbis, PagePacing::MaxPageGranules(u64::MAX));
+	assert!(pages.len() <= 4);
+	assert_eq!(pages.last().unwrap().absgp(), 100 * 1024);
+
+	// Pages larger than the size still end where the input does
+	let (_, pages) = repaginated(&vorbis, PagePacing::MaxPageBytes(std::num::NonZeroUsize::new(400).unwrap()));
+	for page in &pages {
+		assert!(input.contains(&page.absgp()))
+	}
+}
+
+#[test]
+fn repaginate_keeps_page_order() {
+	let one_per_page = |bytes: Vec<u8>| {
+		let mut output = vec![];
+		repaginate(&bytes[..], &mut output, PagePacing::OnePacketPerPage).unwrap();
+		output
+	};
+	let long = one_per_page(opus_stream(1, &[], 100));
+	let short = one_per_page(opus_stream(3, &[], 10));
+
+	// Both pages end with the tenth packet of their stream, which
+	// is read first for the long stream
+	let mut output = vec![];
+	repaginate(&multiplex(&[&long, &short])[..], &mut output, PagePacing::MaxPageGranules(10000)).unwrap();
+	let pages: Vec<Page> = PageReader::new(&output[..]).unwrap().map(|page| page.unwrap().1).collect();
+	let position = |serial: i32| pages.iter()
+		.position(|page| page.stream_serial() == serial && page.absgp() == 9600)
+		.unwrap();
+	assert!(position(1) < position(3));
+}
+
+#[test]
+fn repaginate_joined_stream() {
+	// Without the header pages, every packet is data
+	let mut opus = vec![];
+	repaginate(&opus_stream(1, &[], 100)[..], &mut opus, PagePacing::OnePacketPerPage).unwrap();
+	let pages: Vec<Page> = PageReader::new(&opus[..]).unwrap().map(|page| page.unwrap().1).collect();
+	let mut joined = vec![];
+	for page in &pages[2..] {
+		joined.extend_from_slice(page.header());
+		joined.extend_from_slice(page.data());
+	}
+
+	let mut output = vec![];
+	let summary = repaginate(&joined[..], &mut output, PagePacing::OnePacketPerPage).unwrap();
+	assert_eq!(summary.packets, 100);
+	let output: Vec<Page> = PageReader::new(&output[..]).unwrap().map(|page| page.unwrap().1).collect();
+	for page in &output {
+		assert!(pages[2..].iter().any(|input| input.absgp() == page.absgp()))
+	}
+	assert_eq!(output[0].absgp(), 960);
+}
+
+#[test]
+fn repaginate_large_group() {
+	// Only the last packet has a granule position, so every
+	// packet is held until it's read
+	let mut packets = vec![Packet::with_data(b"header".to_vec())];
+	for index in 0..20 {
+		let mut packet = Packet::with_data(vec![index; 7000]);
+		packet.set_absgp(if index == 19 { 1000 } else { u64::MAX });
+		packet.set_ends_logical_stream(index == 19);
+		packets.push(packet)
+	}
+	let bytes = encode_packets(4, &packets);
+
+	let mut output = vec![];
+	repaginate(&bytes[..], &mut output, PagePacing::OnePacketPerPage).unwrap();
+	assert_eq!(decode_packets(&output).len(), 21);
+	let pages: Vec<Page> = PageReader::new(&output[..]).unwrap().map(|page| page.unwrap().1).collect();
+	assert!(pages.len() > 2);
+	for page in &pages {
+		assert!(page.finished_packets() == 0 || page.absgp() != u64::MAX)
+	}
+	assert_eq!(pages.last().unwrap().absgp(), 1000);
+}
+
+/// Encode packets of `size` bytes, each 20 ms at 48 kHz, with a
+/// page policy, and return the pages.
+fn pages_with_policy<P: PagePolicy + Send + 'static>(policy: P, count: u64, size: usize) -> Vec<Page> {
+	let mut stream = Stream::new(5).unwrap();
+	stream.set_page_policy(policy);
+	let mut pages = vec![];
+	for index in 0..count {
+		let mut packet = Packet::with_data(vec![0xF8; size]);
+		packet.set_absgp(index * 960);
+		packet.set_ends_logical_stream(index + 1 == count);
+		stream.packet_in(&packet).unwrap();
+		pages.extend(stream.pages().map(Result::unwrap));
+	}
+	while let Ok(page) = stream.page_flush() {
+		pages.push(page.clone())
+	}
+	assert_eq!(stream.pending().packets, 0);
+	pages
+}
+
+#[test]
+fn page_policies() {
+	let pages = pages_with_policy(LowLatency::default(), 20, 4);
+	assert_eq!(pages.len(), 20);
+	assert!(pages.iter().all(|page| page.finished_packets() == 1));
+
+	// Two segments for every packet, 255 segments to a page
+	let pages = pages_with_policy(MinimalOverhead, 300, 300);
+	assert_eq!(pages.len(), 4);
+	assert_eq!(pages[1].finished_packets(), 127);
+
+	// Without a policy, these would fill 255 segments
+	let pages = pages_with_policy(OpusRecommended, 300, 4);
+	let mut last = 0;
+	for page in &pages[1..] {
+		assert!(page.absgp() - last <= 48000);
+		last = page.absgp()
+	}
+	assert_eq!(pages[1].absgp(), 50 * 960);
+	assert_eq!(last, 299 * 960);
+}
+
+#[test]
+fn write_headers_on_own_pages() {
+	let vorbis = decode_packets(&vorbis_like_stream(3, 10));
+	let mut writer = PacketWriter::new(vec![], 3).unwrap();
+	for packet in &vorbis[..3] {
+		writer.write_header(packet).unwrap()
+	}
+	for packet in &vorbis[3..] {
+		writer.write_packet(packet).unwrap()
+	}
+	assert!(matches!(
+		writer.write_header(&vorbis[0]),
+		Err(Error::PacketWrite(PacketWriteError::HeaderAfterData))
+	));
+	let bytes = writer.into_inner().unwrap();
+
+	let pages: Vec<Page> = PageReader::new(&bytes[..]).unwrap().map(|page| page.unwrap().1).collect();
+	let packets: Vec<u8> = pages.iter().map(|page| page.finished_packets()).collect();
+	assert_eq!(packets, vec![1, 2, 10]);
+	assert_eq!(decode_packets(&bytes).len(), 13);
+
+	// The tags of Opus are alone too, even when small
+	let opus = decode_packets(&opus_stream(4, &[], 3));
+	let mut writer = PacketWriter::new(vec![], 4).unwrap();
+	writer.write_header(&opus[0]).unwrap();
+	writer.write_header(&opus[1]).unwrap();
+	writer.write_packet(&opus[2]).unwrap();
+	let bytes = writer.into_inner().unwrap();
+	let packets: Vec<u8> = PageReader::new(&bytes[..]).unwrap().map(|page| page.unwrap().1.finished_packets()).collect();
+	assert_eq!(packets, vec![1, 1, 1]);
+}
+
+/// Read every packet of a single logical stream through a [Timestamper].
+fn timestamped(bytes: &[u8]) -> (Timestamper, Vec<Packet>) {
+	let mut pages = PageReader::new(bytes).unwrap();
+	let (_, mut page) = pages.next_page().unwrap().unwrap();
+	let mut timestamper = Timestamper::new(page.stream_serial()).unwrap();
+	let mut packets = vec![];
 	loop {
-		match stream.packet_out() {
-			Ok(packet) => packets.push(packet.clone()),
-			Err(packet_out_error) => match packet_out_error  {
-				PacketOutError::OutOfSync => panic!("stream.packet_out returned sync error"),
-				PacketOutError::NoPages => unreachable!(),
-				PacketOutError::InternalError => break
-			}
+		timestamper.page_in(&mut page).unwrap();
+		packets.extend(timestamper.packets());
+		page = match pages.next_page().unwrap() {
+			None => break,
+			Some((_, page)) => page
 		}
 	}
-	println!("found {} packets", packets.len())
+	(timestamper, packets)
+}
+
+#[test]
+fn timestamp_opus_packets() {
+	let (timestamper, packets) = timestamped(&opus_stream(6, &[], 200));
+	assert_eq!(timestamper.codec(), Codec::Opus);
+	assert_eq!(timestamper.start(), Some(0));
+	assert_eq!(packets.len(), 202);
+	assert_eq!(packets[0].absgp(), 0);
+	assert_eq!(packets[1].absgp(), 0);
+	for (frame, packet) in packets[2..].iter().enumerate() {
+		assert_eq!(packet.absgp(), (frame as u64 + 1) * 960)
+	}
+}
+
+/// Write values into bytes, least significant bit first.
+struct BitWriter {
+	bytes: Vec<u8>,
+	bits: usize
+}
+
+impl BitWriter {
+	fn write(&mut self, value: u64, bits: usize) {
+		for bit in 0..bits {
+			if self.bits.is_multiple_of(8) { self.bytes.push(0) }
+			*self.bytes.last_mut().unwrap() |= (((value >> bit) & 1) as u8) << (self.bits % 8);
+			self.bits += 1
+		}
+	}
+}
+
+/// Return the header packets of a Vorbis stream at 48 kHz with
+/// block sizes of 256 and 2048, and a short and a long mode. An
+/// audio packet uses the long one if its first byte is 2.
+fn vorbis_headers() -> Vec<Packet> {
+	let mut id = b"\x01vorbis".to_vec();
+	id.extend_from_slice(&[0, 0, 0, 0, 1]);
+	id.extend_from_slice(&48000u32.to_le_bytes());
+	id.extend_from_slice(&[0; 12]);
+	id.extend_from_slice(&[0xB8, 1]);
+	// The modes are at the end of the setup header
+	let mut setup = BitWriter { bytes: b"\x05vorbis\xFF\xFF\xFF".to_vec(), bits: 80 };
+	setup.write(1, 6);
+	for long in [0, 1] {
+		setup.write(long, 1);
+		setup.write(0, 16);
+		setup.write(0, 16);
+		setup.write(0, 8)
+	}
+	setup.write(1, 1);
+
+	vec![
+		Packet::with_data(id),
+		Packet::with_data(b"\x03vorbis\0\0\0\0\0\0\0\0".to_vec()),
+		Packet::with_data(setup.bytes)
+	]
+}
+
+#[test]
+fn timestamp_vorbis_packets() {
+	let mut packets = vorbis_headers();
+	let mut expected = vec![];
+	let mut granule = 0;
+	let mut previous: Option<u64> = None;
+	for index in 0..100 {
+		let long = index % 3 != 0;
+		let size = if long { 2048 } else { 256 };
+		granule += previous.map(|previous| previous / 4 + size / 4).unwrap_or(0);
+		previous = Some(size);
+		let mut packet = Packet::with_data(vec![(long as u8) << 1; 200]);
+		packet.set_absgp(granule);
+		expected.push(granule);
+		packets.push(packet)
+	}
+	// The last packet is cut short
+	let last = packets.last_mut().unwrap();
+	last.set_absgp(granule - 100);
+	last.set_ends_logical_stream(true);
+	*expected.last_mut().unwrap() -= 100;
+
+	let (timestamper, decoded) = timestamped(&encode_packets(7, &packets));
+	assert_eq!(timestamper.codec(), Codec::Vorbis);
+	assert_eq!(timestamper.start(), Some(0));
+	assert_eq!(timestamper.start_trim(), Some(0));
+	assert_eq!(timestamper.end_trim(), Some(100));
+	let granules: Vec<u64> = decoded[3..].iter().map(|packet| packet.absgp()).collect();
+	assert_eq!(granules, expected);
+}
+
+#[test]
+fn timestamp_flac_packets() {
+	let mut streaminfo = b"\x7fFLAC\x01\x00\x00\x01fLaC\x00\x00\x00\x22".to_vec();
+	streaminfo.extend_from_slice(&[0; 10]);
+	// 44.1 kHz in the first 20 bits
+	streaminfo.extend_from_slice(&[0x0A, 0xC4, 0x40]);
+	streaminfo.extend_from_slice(&[0; 21]);
+	let mut packets = vec![
+		Packet::with_data(streaminfo),
+		Packet::with_data(b"\x84\x00\x00\x00".to_vec())
+	];
+
+	// The block size code, the coded frame number, the block size
+	// stored after it, and the number of samples
+	let frames: [(u8, &[u8], &[u8], u64); 7] = [
+		(1, &[0x00], &[], 192),
+		(3, &[0x01], &[], 1152),
+		(6, &[0x02], &[99], 100),
+		(7, &[0x03], &[0x0F, 0xFF], 4096),
+		(6, &[0xC2, 0x80], &[0xFF], 256),
+		(7, &[0xE0, 0xA0, 0x80], &[0x01, 0x2B], 300),
+		(12, &[0xE0, 0xA0, 0x81], &[], 4096)
+	];
+	let mut expected = vec![];
+	let mut granule = 0;
+	for (code, number, size, samples) in frames {
+		let mut frame = vec![0xFF, 0xF8, code << 4 | 0x09, 0x08];
+		frame.extend_from_slice(number);
+		frame.extend_from_slice(size);
+		frame.resize(1500, 0);
+		granule += samples;
+		expected.push(granule);
+		let mut packet = Packet::with_data(frame);
+		packet.set_absgp(granule);
+		packets.push(packet)
+	}
+	packets.last_mut().unwrap().set_ends_logical_stream(true);
+
+	let (timestamper, decoded) = timestamped(&encode_packets(8, &packets));
+	assert_eq!(timestamper.codec(), Codec::Flac);
+	assert_eq!(timestamper.start(), Some(0));
+	assert_eq!(timestamper.start_trim(), Some(0));
+	assert_eq!(timestamper.end_trim(), Some(0));
+	let granules: Vec<u64> = decoded[2..].iter().map(|packet| packet.absgp()).collect();
+	assert_eq!(granules, expected);
+}
+
+#[test]
+fn timestamp_pcm_packets() {
+	// The format code, the number of channels and the bytes every
+	// sample of a channel takes
+	for (format, channels, bytes) in [(0x00u32, 1u8, 1u64), (0x03, 2, 2)] {
+		let mut header = b"PCM     \x00\x00\x00\x00".to_vec();
+		header.extend_from_slice(&format.to_be_bytes());
+		header.extend_from_slice(&44100u32.to_be_bytes());
+		header.extend_from_slice(&[8 * bytes as u8, channels, 0, 0]);
+		header.extend_from_slice(&0u32.to_be_bytes());
+		let mut packets = vec![Packet::with_data(header), Packet::with_data(vec![0; 8])];
+
+		let mut expected = vec![];
+		let mut granule = 0;
+		for index in 0..20 {
+			let samples = 100 + index * 10;
+			granule += samples;
+			expected.push(granule);
+			let mut packet = Packet::with_data(vec![0; (samples * channels as u64 * bytes) as usize]);
+			packet.set_absgp(granule);
+			packet.set_ends_logical_stream(index == 19);
+			packets.push(packet)
+		}
+
+		let (timestamper, decoded) = timestamped(&encode_packets(9, &packets));
+		assert_eq!(timestamper.codec(), Codec::Pcm);
+		assert_eq!(timestamper.start(), Some(0));
+		let granules: Vec<u64> = decoded[2..].iter().map(|packet| packet.absgp()).collect();
+		assert_eq!(granules, expected);
+	}
+}
+
+#[test]
+fn gapless_trims_of_every_stream() {
+	// Starts 500 samples before 0, on a page before the last
+	let mut packets = decode_packets(&opus_stream(2, &[], 400));
+	for packet in &mut packets[2..] {
+		packet.set_absgp(packet.absgp().saturating_sub(500))
+	}
+	let trimmed = encode_packets(2, &packets);
+	let bytes = multiplex(&[&opus_stream(1, &[], 400), &trimmed]);
+
+	assert_eq!(gapless_trims(&bytes[..]).unwrap(), vec![
+		Trims { serial: 1, codec: Codec::Opus, start: Some(312), end: Some(0) },
+		Trims { serial: 2, codec: Codec::Opus, start: Some(312 + 500), end: Some(0) }
+	]);
+}
+
+#[test]
+fn duration_of_file() {
+	let found = duration(std::io::Cursor::new(include_bytes!("../sine.ogg"))).unwrap();
+	assert_eq!(found.streams.len(), 1);
+	assert_eq!(found.streams[0].codec, Codec::Opus);
+	assert_eq!(found.streams[0].start, Some(0));
+	assert_eq!(found.streams[0].absgp, Some(240312));
+	assert_eq!(found.seconds, 5.0);
+}
+
+#[test]
+fn duration_from_start_granule() {
+	// A logical stream recorded from the middle of a broadcast
+	let mut packets = decode_packets(&opus_stream(5, &[], 500));
+	for (frame, packet) in packets[2..].iter_mut().enumerate() {
+		packet.set_absgp(480000 + (frame as u64 + 1) * 960)
+	}
+
+	let found = duration(std::io::Cursor::new(encode_packets(5, &packets))).unwrap();
+	assert_eq!(found.streams[0].start, Some(480000));
+	assert_eq!(found.streams[0].absgp, Some(480000 + 500 * 960));
+	assert_eq!(found.seconds, (500.0 * 960.0 - 312.0) / 48000.0);
+}
+
+#[test]
+fn duration_of_chained_file() {
+	// Links longer than a chunk, followed by bytes that aren't a page
+	let mut bytes = opus_stream(1, &[], 40000);
+	bytes.extend_from_slice(&opus_stream(2, &[], 100000));
+	bytes.extend_from_slice(&multiplex(&[&opus_stream(3, &[], 500), &opus_stream(4, &[], 1000)]));
+	bytes.extend_from_slice(b"trailing garbage");
+
+	let found = duration(std::io::Cursor::new(&bytes)).unwrap();
+	let streams: Vec<(usize, i32, Option<u64>)> = found.streams.iter()
+		.map(|stream| (stream.link, stream.serial, stream.absgp))
+		.collect();
+	assert_eq!(streams, vec![
+		(0, 1, Some(40000 * 960)),
+		(1, 2, Some(100000 * 960)),
+		(2, 3, Some(500 * 960)),
+		(2, 4, Some(1000 * 960))
+	]);
+	let seconds = (40000.0 * 960.0 - 312.0) / 48000.0 + (100000.0 * 960.0 - 312.0) / 48000.0 + (1000.0 * 960.0 - 312.0) / 48000.0;
+	assert!((found.seconds - seconds).abs() < 1e-9);
+}
+
+#[test]
+fn seconds_between_theora_granules() {
+	// Frame 13 is 3 after the keyframe at 10, frame 55 is 5
+	// after the keyframe at 50
+	let theora = Timebase { numerator: 25, denominator: 1, keyframe_shift: 6, pre_skip: 0 };
+	assert_eq!(theora.seconds_between((10 << 6) + 3, (50 << 6) + 5), 42.0 / 25.0);
+	assert_eq!(theora.seconds_between(0, (50 << 6) + 5), theora.seconds((50 << 6) + 5));
+}
+
+#[test]
+fn duration_of_same_serial_chain() {
+	// Both links have the same serial number
+	let mut bytes = include_bytes!("../sine.ogg").to_vec();
+	bytes.extend_from_slice(include_bytes!("../sine.ogg"));
+
+	let found = duration(std::io::Cursor::new(&bytes)).unwrap();
+	let streams: Vec<(usize, Option<u64>)> = found.streams.iter()
+		.map(|stream| (stream.link, stream.absgp))
+		.collect();
+	assert_eq!(streams, vec![(0, Some(240312)), (1, Some(240312))]);
+	assert_eq!(found.streams[0].serial, found.streams[1].serial);
+	assert_eq!(found.seconds, 10.0);
+}
+
+#[test]
+fn page_index_sidecar() {
+	let bytes = include_bytes!("../sine.ogg");
+	let index = PageIndex::build(&bytes[..]).unwrap();
+	assert_eq!(index.entries().len(), 8);
+	assert_eq!(index.file_size(), bytes.len() as u64);
+	let first = index.entries()[0];
+	assert_eq!((first.offset, first.index, first.absgp, first.flags, first.packets), (0, 0, 0, 0x02, 1));
+	let last = index.entries()[7];
+	assert_eq!((last.index, last.absgp, last.flags), (7, 240312, 0x04));
+	assert_eq!(last.offset + last.length as u64, bytes.len() as u64);
+	assert_eq!(index.find_granule(first.serial, 240312).unwrap().index, 7);
+	assert_eq!(index.find_granule(first.serial, 1).unwrap().index, 2);
+	assert_eq!(index.find_granule(first.serial, 240313), None);
+	assert_eq!(index.find_granule(first.serial + 1, 0), None);
+	for entry in index.entries() {
+		assert_eq!(index.find_granule(first.serial, entry.absgp).unwrap().absgp, entry.absgp)
+	}
+
+	let mut sidecar = vec![];
+	index.write_to(&mut sidecar).unwrap();
+	assert_eq!(sidecar.len(), 26 + 8 * 30);
+	let loaded = PageIndex::load(&sidecar[..], std::io::Cursor::new(&bytes[..])).unwrap();
+	assert_eq!(loaded, index);
+
+	// A changed source file
+	let mut changed = bytes.to_vec();
+	changed.extend_from_slice(b"more");
+	let result = PageIndex::load(&sidecar[..], std::io::Cursor::new(&changed));
+	assert!(matches!(result, Err(Error::Index(IndexError::Stale))));
+	let result = PageIndex::read_from(&bytes[..]);
+	assert!(matches!(result, Err(Error::Index(IndexError::NotAnIndex))));
+
+	// A page count that doesn't match the entries
+	let mut wrong = sidecar.clone();
+	wrong[18..26].copy_from_slice(&u64::MAX.to_le_bytes());
+	let result = PageIndex::read_from(&wrong[..]);
+	assert!(matches!(result, Err(Error::Index(IndexError::WrongLength))));
+	let result = PageIndex::read_from(&sidecar[..sidecar.len() - 1]);
+	assert!(matches!(result, Err(Error::Index(IndexError::WrongLength))));
+	// A source file shorter than the last page
+	let mut wrong = sidecar.clone();
+	wrong[6..14].copy_from_slice(&(bytes.len() as u64 - 1).to_le_bytes());
+	let result = PageIndex::read_from(&wrong[..]);
+	assert!(matches!(result, Err(Error::Index(IndexError::PageOutsideFile))));
+}
+
+/// A source that counts every range request made to it, like a
+/// server taking HTTP range requests.
+struct RangeServer {
+	data: Vec<u8>,
+	requests: std::sync::atomic::AtomicUsize
+}
+
+impl RangeServer {
+	fn new(data: Vec<u8>) -> Self {
+		Self { data, requests: std::sync::atomic::AtomicUsize::new(0) }
+	}
+
+	fn requests(&self) -> usize {
+		self.requests.load(std::sync::atomic::Ordering::SeqCst)
+	}
+}
+
+impl ReadAt for RangeServer {
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+		self.data.read_at(offset, buf)
+	}
+
+	fn size(&self) -> std::io::Result<u64> {
+		self.requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+		Ok(self.data.len() as u64)
+	}
+}
+
+#[test]
+fn read_at_sources() {
+	let bytes = include_bytes!("../sine.ogg");
+	let file = std::fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/sine.ogg")).unwrap();
+	assert_eq!(file.size().unwrap(), bytes.len() as u64);
+	let mut capture = [0; 4];
+	file.read_exact_at(0, &mut capture).unwrap();
+	assert_eq!(&capture, b"OggS");
+	assert_eq!(duration(ReadAtCursor::new(&file)).unwrap(), duration(ReadAtCursor::new(&bytes[..])).unwrap());
+
+	let mut sidecar = vec![];
+	PageIndex::build(&bytes[..]).unwrap().write_to(&mut sidecar).unwrap();
+	PageIndex::load(&sidecar[..], ReadAtCursor::new(&bytes[..])).unwrap();
+}
+
+#[test]
+fn cached_reader_saves_requests() {
+	let mut bytes = opus_stream(1, &[], 20000);
+	bytes.extend_from_slice(&opus_stream(2, &[], 20000));
+	let expected = duration(std::io::Cursor::new(&bytes)).unwrap();
+
+	let server = RangeServer::new(bytes.clone());
+	assert_eq!(duration(ReadAtCursor::new(&server)).unwrap(), expected);
+	let uncached = server.requests();
+
+	let server = RangeServer::new(bytes);
+	let cached = CachedReader::new(&server, 1 << 16, 8);
+	assert_eq!(duration(ReadAtCursor::new(&cached)).unwrap(), expected);
+	assert!(server.requests() * 4 < uncached, "{} requests with a cache, {} without", server.requests(), uncached);
+
+	// Blocks that are kept aren't requested again
+	let requests = server.requests();
+	let mut start = [0; 100];
+	cached.read_exact_at(0, &mut start).unwrap();
+	cached.read_exact_at(50, &mut start).unwrap();
+	assert!(server.requests() <= requests + 1);
+	let before = server.requests();
+	cached.read_exact_at(10, &mut start).unwrap();
+	assert_eq!(server.requests(), before);
+	assert_eq!(&start[..4], &server.data[10..14]);
+
+	// Blocks that aren't kept are requested together
+	let cached = CachedReader::new(&server, 1024, 64);
+	let mut data = vec![0; 10000];
+	let before = server.requests();
+	cached.read_exact_at(100, &mut data[..10]).unwrap();
+	cached.read_exact_at(5000, &mut data[..10]).unwrap();
+	cached.read_exact_at(100, &mut data).unwrap();
+	assert_eq!(server.requests(), before + 4);
+	assert_eq!(data, server.data[100..10100]);
+}
+
+#[test]
+fn scan_pages_in_place() {
+	let bytes = include_bytes!("../sine.ogg");
+	let expected = SyncState::new().unwrap().submit_bytes(bytes).unwrap().unwrap();
+	let pages: Vec<(u64, PageRef)> = scan_pages(bytes).collect();
+	assert_eq!(pages.len(), expected.len());
+	let mut offset = 0;
+	for ((page_offset, page), expected) in pages.iter().zip(&expected) {
+		assert_eq!(*page_offset, offset);
+		assert_eq!(page.header(), expected.header());
+		assert_eq!(page.data(), expected.data());
+		assert_eq!((page.absgp(), page.index(), page.finished_packets()), (expected.absgp(), expected.index(), expected.finished_packets()));
+		assert!(page.verify_crc_checksum());
+		offset += page.size() as u64
+	}
+	assert_eq!(pages[0].1.to_page().header(), expected[0].header());
+	assert_eq!(PageIndex::scan(bytes), PageIndex::build(&bytes[..]).unwrap());
+
+	// Garbage, a page with a bad checksum and a cut off page are skipped
+	let second = pages[1].0 as usize;
+	let mut damaged = b"garbage OggS".to_vec();
+	damaged.extend_from_slice(bytes);
+	damaged[12 + second + pages[1].1.header().len()] ^= 0xFF;
+	damaged.truncate(damaged.len() - 10);
+	let mut scanner = scan_pages(&damaged);
+	let offsets: Vec<u64> = scanner.by_ref().map(|(offset, _)| offset).collect();
+	let expected_offsets: Vec<u64> = pages.iter().map(|(offset, _)| offset + 12).filter(|offset| *offset != second as u64 + 12).collect();
+	assert_eq!(offsets, expected_offsets[..expected_offsets.len() - 1]);
+	assert_eq!(scanner.skipped(), 12 + pages[1].1.size() as u64 + pages[7].1.size() as u64 - 10);
+	assert_eq!(PageRef::parse(&bytes[..40]), Err(InvalidPageHeader::BadLength));
+	assert_eq!(PageRef::parse(&bytes[1..]), Err(InvalidPageHeader::NoMagicString));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn scan_mapped_file() {
+	let file = std::fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/sine.ogg")).unwrap();
+	let map = unsafe { MappedFile::map(&file) }.unwrap();
+	let bytes = include_bytes!("../sine.ogg");
+	assert_eq!(map.bytes(), &bytes[..]);
+	assert_eq!(map.pages().count(), 8);
+	assert_eq!(PageIndex::scan(map.bytes()), PageIndex::build(&bytes[..]).unwrap());
+	assert_eq!(duration(ReadAtCursor::new(&map)).unwrap(), duration(std::io::Cursor::new(bytes)).unwrap());
 }
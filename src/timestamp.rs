@@ -0,0 +1,265 @@
+use std::{
+	collections::{ HashMap, VecDeque },
+	io::Read
+};
+
+use crate::{ Codec, Error, Packet, Page, PageReader, Stream, Timebase };
+use crate::mapping::PacketDurations;
+
+/// Decodes the packets of a logical stream like a [Stream], but
+/// gives every packet a granule position.
+/// 
+/// Ogg only stores the granule position of the last packet that
+/// finishes on each page. The rest are worked out from how long
+/// every packet lasts, which is read from the packets of Opus,
+/// Vorbis, FLAC, Speex and PCM:
+/// 
+/// - On most pages, granule positions are counted back from the
+///   granule position of the page. On the first page with data,
+///   this also finds where the logical stream starts, see
+///   [start](Timestamper::start).
+/// - On the last page, they are counted forward from the page
+///   before, since the last packet can be cut short.
+/// 
+/// Header packets are given the granule position 0. Packets
+/// whose granule position can't be worked out keep `-1` (as u64).
+pub struct Timestamper {
+	stream: Stream,
+	codec: Codec,
+	durations: Option<PacketDurations>,
+	header_packets: Option<usize>,
+	/// The number of packets read so far.
+	packets: usize,
+	/// The granule position of the last page that finished a
+	/// data packet.
+	last_granule: Option<u64>,
+	start: Option<u64>,
+	/// The pre-skip from the first header.
+	pre_skip: u64,
+	start_trim: Option<u64>,
+	end_trim: Option<u64>,
+	/// How long the data packets that finished on the last page
+	/// read are, if every one of them had a known duration.
+	page_duration: Option<u64>,
+	ready: VecDeque<Packet>
+}
+
+impl Timestamper {
+	/// Return a new `Timestamper` for the logical stream with the
+	/// serial number `serial`.
+	pub fn new(serial: i32) -> Result<Self, Error> {
+		Ok(Self {
+			stream: Stream::new(serial)?,
+			codec: Codec::Unknown,
+			durations: None,
+			header_packets: None,
+			packets: 0,
+			last_granule: None,
+			start: None,
+			pre_skip: 0,
+			start_trim: None,
+			end_trim: None,
+			page_duration: Some(0),
+			ready: VecDeque::new()
+		})
+	}
+
+	/// Return the codec of the logical stream, as found from its
+	/// first packet. It's [Codec::Unknown] if the first page read
+	/// doesn't begin the logical stream.
+	pub fn codec(&self) -> Codec {
+		self.codec
+	}
+
+	/// Return the granule position the first data packet starts
+	/// at, once the first page with data was read and every packet
+	/// on it had a known duration.
+	pub fn start(&self) -> Option<u64> {
+		self.start
+	}
+
+	/// Check whether a page that finished a data packet and has
+	/// a granule position was read.
+	pub(crate) fn started(&self) -> bool {
+		self.last_granule.is_some()
+	}
+
+	/// Return the number of granule units to drop at the start of
+	/// the decoded data for gapless playback.
+	/// 
+	/// This is the pre-skip of Opus, along with any data the first
+	/// page with data decodes to beyond its granule position. It's
+	/// known once that page was read and every packet on it had a
+	/// known duration.
+	pub fn start_trim(&self) -> Option<u64> {
+		self.start_trim
+	}
+
+	/// Return the number of granule units to drop at the end of
+	/// the decoded data for gapless playback.
+	/// 
+	/// This is how far the data decodes beyond the granule position
+	/// of the last page. It's known once the end of stream page was
+	/// read and every packet on it had a known duration.
+	pub fn end_trim(&self) -> Option<u64> {
+		self.end_trim
+	}
+
+	/// Return the number of granule units the data packets that
+	/// finished on the last page decode to, if they all had a
+	/// known duration.
+	pub(crate) fn page_duration(&self) -> Option<u64> {
+		self.page_duration
+	}
+
+	/// Check whether a packet is a header, before it is counted.
+	fn is_header(&self, packet: &Packet) -> bool {
+		match self.header_packets {
+			Some(headers) => self.packets < headers,
+			// FLAC frames start with a sync code, metadata
+			// blocks can't
+			None if self.codec == Codec::Flac => packet.data().first() != Some(&0xFF),
+			None => self.packets == 0
+		}
+	}
+
+	/// Add a `Page` to the `Timestamper`, and give every packet
+	/// that finishes on it a granule position.
+	pub fn page_in(&mut self, page: &mut Page) -> Result<(), Error> {
+		self.stream.page_in(page)?;
+		// Packets lost to holes are skipped
+		let packets: Vec<Packet> = self.stream.packets().flatten().collect();
+		if self.durations.is_none() && !page.begins_logical_stream() {
+			// Joined after the headers, so the codec isn't known
+			self.header_packets = Some(0);
+			self.durations = Some(PacketDurations::new(Codec::Unknown, &[]));
+		}
+
+		let mut data = vec![];
+		for mut packet in packets {
+			if self.durations.is_none() {
+				self.codec = Codec::identify(packet.data());
+				self.header_packets = self.codec.header_packets(packet.data());
+				self.durations = Some(PacketDurations::new(self.codec, packet.data()));
+				self.pre_skip = Timebase::from_header(self.codec, packet.data()).map(|timebase| timebase.pre_skip).unwrap_or(0);
+			}
+			let header = self.is_header(&packet);
+			self.packets += 1;
+
+			let durations = self.durations.as_mut().unwrap();
+			if header {
+				if self.packets > 1 { durations.header(packet.data()) }
+				packet.set_absgp(0);
+				self.ready.push_back(packet)
+			} else {
+				let duration = durations.duration(packet.data());
+				data.push((packet, duration))
+			}
+		}
+
+		self.page_duration = data.iter().map(|(_, duration)| *duration).sum();
+		let absgp = page.absgp();
+		if data.is_empty() || absgp == u64::MAX {
+			if page.ends_logical_stream() && self.last_granule.is_some() {
+				self.end_trim = Some(0)
+			}
+			self.ready.extend(data.into_iter().map(|(packet, _)| packet));
+			return Ok(())
+		}
+
+		// A first page that decodes to more than its granule position
+		// is trimmed at the start, unless it's also the last page
+		let decoded = self.page_duration;
+		match self.last_granule {
+			None if page.ends_logical_stream() => {
+				self.start_trim = Some(self.pre_skip);
+				self.end_trim = decoded.map(|decoded| decoded.saturating_sub(absgp))
+			},
+			None => self.start_trim = decoded.map(|decoded| self.pre_skip + decoded.saturating_sub(absgp)),
+			Some(last) if page.ends_logical_stream() => {
+				self.end_trim = decoded.map(|decoded| (last + decoded).saturating_sub(absgp))
+			},
+			Some(_) => {}
+		}
+
+		let known = data.iter().all(|(_, duration)| duration.is_some());
+		match self.last_granule {
+			Some(mut granule) if page.ends_logical_stream() && known => {
+				let last = data.len() - 1;
+				for (index, (packet, duration)) in data.iter_mut().enumerate() {
+					granule += duration.unwrap();
+					packet.set_absgp(if index == last { absgp } else { granule.min(absgp) })
+				}
+			},
+			_ => {
+				let mut end = Some(absgp);
+				for (packet, duration) in data.iter_mut().rev() {
+					packet.set_absgp(end.unwrap_or(u64::MAX));
+					end = end.zip(*duration).map(|(end, duration)| end.saturating_sub(duration))
+				}
+				if self.last_granule.is_none() { self.start = end }
+			}
+		}
+
+		self.last_granule = Some(absgp);
+		self.ready.extend(data.into_iter().map(|(packet, _)| packet));
+		Ok(())
+	}
+
+	/// Take the next packet out of the `Timestamper`.
+	pub fn packet_out(&mut self) -> Option<Packet> {
+		self.ready.pop_front()
+	}
+
+	/// Return an iterator taking every packet out of the
+	/// `Timestamper`.
+	pub fn packets(&mut self) -> impl Iterator<Item = Packet> + '_ {
+		self.ready.drain(..)
+	}
+}
+
+/// How many granule units to drop at the start and end of a
+/// logical stream for gapless playback, as found by [Timestamper].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trims {
+	pub serial: i32,
+	pub codec: Codec,
+	/// See [Timestamper::start_trim].
+	pub start: Option<u64>,
+	/// See [Timestamper::end_trim].
+	pub end: Option<u64>
+}
+
+/// Find the [Trims] of every logical stream in a physical stream,
+/// in the order they begin.
+/// 
+/// Every page is read, since the end trim depends on the packets
+/// of the last page and the granule position of the one before.
+pub fn gapless_trims<R: Read>(reader: R) -> Result<Vec<Trims>, Error> {
+	let mut pages = PageReader::new(reader)?;
+	let mut streams: HashMap<i32, (usize, Timestamper)> = HashMap::new();
+	let mut trims = vec![];
+
+	while let Some((_, mut page)) = pages.next_page()? {
+		let serial = page.stream_serial();
+		if page.begins_logical_stream() || !streams.contains_key(&serial) {
+			streams.insert(serial, (trims.len(), Timestamper::new(serial)?));
+			trims.push(Trims { serial, codec: Codec::Unknown, start: None, end: None });
+		}
+
+		let (index, timestamper) = streams.get_mut(&serial).unwrap();
+		timestamper.page_in(&mut page)?;
+		timestamper.packets().for_each(drop);
+		trims[*index] = Trims {
+			serial,
+			codec: timestamper.codec(),
+			start: timestamper.start_trim(),
+			end: timestamper.end_trim()
+		};
+		if page.ends_logical_stream() {
+			streams.remove(&serial);
+		}
+	}
+
+	Ok(trims)
+}
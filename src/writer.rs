@@ -0,0 +1,173 @@
+use std::{
+	io::Write,
+	num::NonZeroUsize
+};
+
+use crate::{ Codec, Error, Packet, PageOutError, Stream };
+
+/// Writes [Packets](Packet) of a logical stream to a [Write].
+/// 
+/// Packets go through a [Stream], and every page it completes
+/// is written right away.
+/// 
+/// ```rust
+/// # use ogg_xiph::{ Packet, PacketWriter };
+/// let mut writer = PacketWriter::new(vec![], 1234)?;
+/// let mut packet = Packet::with_data(b"some data".to_vec());
+/// packet.set_ends_logical_stream(true);
+/// writer.write_packet(&packet)?;
+/// let bytes = writer.into_inner()?;
+/// # Ok::<(), ogg_xiph::Error>(())
+/// ```
+/// 
+/// Header packets can be written with [write_header](PacketWriter::write_header)
+/// to have them paginated as the mapping of their codec requires.
+pub struct PacketWriter<W: Write> {
+	writer: W,
+	stream: Stream,
+	/// The number of header packets the codec has, if it's known.
+	header_packets: Option<usize>,
+	headers: usize,
+	/// Whether any header is written and not on a page yet.
+	headers_pending: bool,
+	data: bool
+}
+
+impl<W: Write> PacketWriter<W> {
+	/// Return a new `PacketWriter` writing a new logical stream
+	/// with the serial number `serial` to `writer`.
+	pub fn new(writer: W, serial: i32) -> Result<Self, Error> {
+		Ok(Self::from_stream(writer, Stream::new(serial)?))
+	}
+
+	/// Return a new `PacketWriter` writing the pages of `stream`
+	/// to `writer`.
+	pub fn from_stream(writer: W, stream: Stream) -> Self {
+		Self {
+			writer,
+			stream,
+			header_packets: None,
+			headers: 0,
+			headers_pending: false,
+			data: false
+		}
+	}
+
+	/// Add a header [Packet] of the logical stream.
+	/// 
+	/// The codec is found from the first header. Pages are
+	/// flushed as every known mapping requires: the first header
+	/// is alone on the first page, and the last header ends a
+	/// page, so data starts on a new one. If the number of headers
+	/// isn't known, they end a page when the first data packet is
+	/// written. Headers are given the granule position 0.
+	/// 
+	/// Returns [PacketWriteError::HeaderAfterData] if a data
+	/// packet was already written.
+	pub fn write_header(&mut self, packet: &Packet) -> Result<(), Error> {
+		if self.data { return Err(PacketWriteError::HeaderAfterData.into()) }
+
+		if self.headers == 0 {
+			self.header_packets = Codec::identify(packet.data()).header_packets(packet.data())
+		}
+		let mut packet = packet.clone();
+		packet.set_absgp(0);
+		self.stream.packet_in(&packet)?;
+		self.headers += 1;
+		self.headers_pending = true;
+
+		if self.headers == 1 || Some(self.headers) == self.header_packets {
+			self.flush_pages()?;
+			self.headers_pending = false
+		}
+		Ok(())
+	}
+
+	/// Add a [Packet] and write every page that is complete.
+	pub fn write_packet(&mut self, packet: &Packet) -> Result<(), Error> {
+		if self.headers_pending {
+			self.flush_pages()?;
+			self.headers_pending = false
+		}
+		self.data = true;
+		self.stream.packet_in(packet)?;
+		loop {
+			match self.stream.page_out() {
+				Ok(page) => {
+					self.writer.write_all(page.header())?;
+					self.writer.write_all(page.data())?
+				},
+				Err(PageOutError::NeedMoreData) => return Ok(()),
+				Err(error) => return Err(error.into())
+			}
+		}
+	}
+
+	/// Write every packet added so far, even if the last page
+	/// could hold more.
+	/// 
+	/// The next packet starts on a new page.
+	pub fn flush_pages(&mut self) -> Result<(), Error> {
+		loop {
+			match self.stream.page_flush() {
+				Ok(page) => {
+					self.writer.write_all(page.header())?;
+					self.writer.write_all(page.data())?
+				},
+				Err(PageOutError::NeedMoreData) => return Ok(()),
+				Err(error) => return Err(error.into())
+			}
+		}
+	}
+
+	/// Write every packet added so far on pages of at most about
+	/// `size` bytes, even if the last page could hold more.
+	pub(crate) fn flush_pages_with_max_size(&mut self, size: NonZeroUsize) -> Result<(), Error> {
+		loop {
+			match self.stream.page_flush_with_max_size(size) {
+				Ok(page) => {
+					self.writer.write_all(page.header())?;
+					self.writer.write_all(page.data())?
+				},
+				Err(PageOutError::NeedMoreData) => return Ok(()),
+				Err(error) => return Err(error.into())
+			}
+		}
+	}
+
+	/// Write every packet added so far, even if the last page
+	/// could hold more, and flush the underlying writer.
+	pub fn flush(&mut self) -> Result<(), Error> {
+		self.flush_pages()?;
+		self.writer.flush()?;
+		Ok(())
+	}
+
+	/// Return a reference to the underlying [Stream].
+	pub fn stream(&self) -> &Stream {
+		&self.stream
+	}
+
+	/// Flush every remaining packet and return the underlying writer.
+	pub fn into_inner(mut self) -> Result<W, Error> {
+		self.flush()?;
+		Ok(self.writer)
+	}
+}
+
+/// An error returned by [PacketWriter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketWriteError {
+	/// A header packet was written after a data packet.
+	HeaderAfterData
+}
+
+impl std::fmt::Display for PacketWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+			Self::HeaderAfterData => write!(f, "a header packet can't follow a data packet")
+		}
+    }
+}
+
+impl std::error::Error for PacketWriteError {}